@@ -0,0 +1,35 @@
+use std::io;
+use std::path::Path;
+use serde::Deserialize;
+
+const ACCOUNTS_FILE : &str = "accounts.json";
+
+// Only the profile name is deserialized. Tokens and everything else in the file are skipped.
+#[derive(Deserialize)]
+struct AccountList {
+    #[serde(default)]
+    accounts: Vec<Account>,
+}
+#[derive(Deserialize)]
+struct Account {
+    profile: Option<Profile>,
+}
+#[derive(Deserialize)]
+struct Profile {
+    name: String,
+}
+
+/// The profile names of the accounts in PrismLauncher, in Prism's order.
+/// Returns nothing if Prism has no accounts yet.
+pub fn read_account_names(prism_path: &Path) -> io::Result<Vec<String>> {
+    let json = match std::fs::read_to_string(prism_path.join(ACCOUNTS_FILE)) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let list: AccountList = serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(list.accounts.into_iter()
+        .filter_map(|a| a.profile.map(|p| p.name))
+        .filter(|name| !name.is_empty())
+        .collect())
+}
@@ -1,6 +1,26 @@
+use std::{collections::{HashMap, HashSet}, io, path::{Path, PathBuf}, time::Duration};
 use eframe::{egui::{*}, Frame};
-use log::{debug, info, error};
-use crate::{log_page::LogPage, steam::{write_steam_shortcuts, DesiredShortcut}};
+use log::{error};
+use steam_shortcuts_util::shortcut::ShortcutOwned;
+use crate::accounts::read_account_names;
+use crate::artwork::{grid_dir, install_artwork};
+use crate::compat::{available_compat_tools, config_vdf_path, write_compat_tools, CompatTool};
+use crate::controller::{controller_config_dir, detect_layout, install_controller_templates, ControllerLayout};
+use crate::collections::{cloud_storage_path, update_cloud_collections};
+use crate::instance_options::InstanceOptions;
+use crate::servers::Server;
+use crate::worlds::{world_icon, WorldShortcut};
+use crate::playtime::PlaytimeDb;
+use crate::backup::BackupSettings;
+use crate::crash::{load_crashes, Crash};
+use crate::history::{ExportHistory, ExportRecord, PlannedShortcut};
+use crate::hooks::Hooks;
+use crate::launch::AlreadyRunning;
+use crate::rules::{now_ms, RuleAction};
+use crate::stats_page::StatsPage;
+use crate::sync::{launched_instance, match_status, orphaned_shortcuts, LaunchTarget, SyncStatus, LAUNCH_COMMAND};
+use crate::{doctor_page::DoctorPage, log_page::LogPage, steam::{read_steam_shortcuts, write_steam_shortcuts, DesiredShortcut}};
+use crate::platform::{FileIo, Platform};
 
 use super::ui::*;
 use super::instances::*;
@@ -13,71 +33,460 @@ const APP_SIDEBAR_WIDTH : f32 = 128.0;
 const APP_LOGO_PADDING : f32 = 12.0;
 pub const APP_HEADER_PADDING : f32 = 20.0;
 
-const INSTANCES_JSON_PATH : &str = "instances\\instgroups.json";
+pub const INSTANCES_DIR : &str = "instances";
+const INSTANCES_JSON_FILE : &str = "instgroups.json";
+#[cfg(target_os = "windows")]
 const PRISMLAUNCHER_EXE_PATH : &str = "prismlauncher.exe";
+#[cfg(not(target_os = "windows"))]
+const PRISMLAUNCHER_EXE_PATH : &str = "prismlauncher";
 
 #[derive(Default)]
 pub struct AppModel {
     pub config: Config,
     pub instances: Vec<Instance>,
-
-    // For logging (TODO)
-    pub log_printout : String,
+    /// Opal shortcuts in Steam whose instance is gone.
+    pub orphaned_shortcuts: Vec<ShortcutOwned>,
+    /// Profile names of the accounts in PrismLauncher.
+    pub accounts: Vec<String>,
+    /// Steam Play tools that can be picked for shortcuts.
+    pub compat_tools: Vec<String>,
+    /// Sessions recorded by `opal launch`.
+    pub playtime: PlaytimeDb,
+    /// Crashes from `opal launch` sessions that haven't been dismissed.
+    pub crashes: Vec<Crash>,
+    /// The last exports, which can be undone.
+    pub export_history: ExportHistory,
+    /// Steam, its processes and its files.
+    pub platform: Platform,
 }
 impl AppModel {
-    pub fn update_instances(&mut self) {
-        let mut instance_path = self.config.prism_main_path.clone();
-        instance_path.push(INSTANCES_JSON_PATH);
+    /// Where Steam's files are read and written.
+    pub fn files(&self) -> &dyn FileIo {
+        self.platform.files.as_ref()
+    }
+
+    /// Opal's data folder, see `Platform::data_dir`.
+    pub fn data_dir(&self) -> io::Result<&Path> {
+        self.platform.data_dir.as_deref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory for this user"))
+    }
+
+    /// Path to the PrismLauncher executable, as written into the shortcuts.
+    pub fn prism_exe_path(&self) -> String {
+        self.config.prism_main_path.to_string_lossy().to_string()
+            + std::path::MAIN_SEPARATOR_STR + PRISMLAUNCHER_EXE_PATH
+    }
+
+    /// What the shortcuts run: PrismLauncher, or Opal itself when the launch wrapper is on.
+    pub fn shortcut_exe_path(&self) -> String {
+        if self.config.launch.use_wrapper {
+            match std::env::current_exe() {
+                Ok(exe) => return exe.to_string_lossy().to_string(),
+                Err(e) => error!("Couldn't find Opal's executable, the shortcuts will start PrismLauncher! {}", e),
+            }
+        }
+        self.prism_exe_path()
+    }
+
+    pub fn instances_json_path(&self) -> PathBuf {
+        self.config.prism_main_path.join(INSTANCES_DIR).join(INSTANCES_JSON_FILE)
+    }
 
-        match get_instances_from_path(instance_path, self.config.include_hidden) {
-            Ok(i) => self.instances = i,
+    /// Re-read the instance list and select instances according to the saved rules
+    /// (if there are any; otherwise everything starts unchecked). Also re-reads what goes with
+    /// them, like accounts and play time. Returns whether the instance list could be read;
+    /// failures to read the rest are only logged.
+    pub fn update_instances(&mut self) -> bool {
+        let listed = match get_instances_from_path(self.instances_json_path(), self.config.include_hidden) {
+            Ok(i) => {
+                self.instances = i;
+                self.config.selection_rules.apply(&mut self.instances);
+                self.update_sync_status();
+                true
+            }
+            Err(e) => {
+                error!("Couldn't update instances! {}", e);
+                false
+            }
+        };
+        self.compat_tools = available_compat_tools(self.files(), &self.config.steam_shortcuts_path);
+        match self.data_dir().and_then(PlaytimeDb::load) {
+            Ok(playtime) => self.playtime = playtime,
             Err(e) => {
-                let msg = format!("\nCouldn't update instances! {}", e);
-                error!("{}", &msg);
-                self.log_printout.push_str(&msg);
-                return;
+                error!("Couldn't read the play time database! {}", e);
+            }
+        }
+        self.crashes = self.data_dir().map(load_crashes).unwrap_or_default();
+        self.update_export_history();
+        match read_account_names(&self.config.prism_main_path) {
+            Ok(accounts) => self.accounts = accounts,
+            Err(e) => {
+                error!("Couldn't read PrismLauncher accounts! {}", e);
+            }
+        }
+        listed
+    }
+
+    /// Select the instances that are already in Steam, so exporting again from the GUI doesn't
+    /// drop them. Instances an Exclude rule matches stay deselected.
+    pub fn keep_exported_selected(&mut self) {
+        let rules = self.config.selection_rules.compile();
+        let now = now_ms();
+        for inst in self.instances.iter_mut() {
+            if inst.status.is_in_steam() && rules.decision(inst, now) != Some(RuleAction::Exclude) {
+                inst.checked = true;
+            }
+        }
+    }
+
+    /// The shortcut Opal would write for `inst`. With accounts set, this is the one for the first.
+    pub fn shortcut_for(&self, inst: &Instance) -> DesiredShortcut {
+        let options = self.instance_options(&inst.folder_name);
+        let target = LaunchTarget { account: options.accounts.first().cloned(), ..LaunchTarget::instance(&inst.folder_name) };
+        self.shortcut_for_target(inst, &target, None)
+    }
+
+    /// The quick-join shortcut for one of the instance's servers, e.g. "ATM9 — Our SMP".
+    pub fn server_shortcut_for(&self, inst: &Instance, server: &Server, account: Option<&str>) -> DesiredShortcut {
+        let target = LaunchTarget {
+            server: Some(server.address.clone()),
+            account: account.map(String::from),
+            ..LaunchTarget::instance(&inst.folder_name)
+        };
+        let label = if server.name.trim().is_empty() { &server.address } else { &server.name };
+        let mut shortcut = self.shortcut_for_target(inst, &target, Some(label.trim()));
+        if let Some(icon) = &server.icon {
+            shortcut.icon = icon.to_string_lossy().to_string();
+        }
+        shortcut
+    }
+
+    /// The shortcut that loads straight into one of the instance's worlds. Uses the world's
+    /// icon unless the instance has an icon of its own.
+    pub fn world_shortcut_for(&self, inst: &Instance, world: &WorldShortcut, account: Option<&str>) -> DesiredShortcut {
+        let target = LaunchTarget {
+            world: Some(world.folder.clone()),
+            account: account.map(String::from),
+            ..LaunchTarget::instance(&inst.folder_name)
+        };
+        let label = if world.name.trim().is_empty() { &world.folder } else { &world.name };
+        let mut shortcut = self.shortcut_for_target(inst, &target, Some(label.trim()));
+        if shortcut.icon.is_empty() {
+            if let Some(icon) = world_icon(&inst.path, &world.folder) {
+                shortcut.icon = icon.to_string_lossy().to_string();
+            }
+        }
+        shortcut
+    }
+
+    /// Every shortcut Opal would write for `inst`: its own, then one per server and world.
+    /// All of them once per account if the instance has several.
+    pub fn shortcuts_for(&self, inst: &Instance) -> Vec<DesiredShortcut> {
+        let options = self.instance_options(&inst.folder_name);
+        let accounts: Vec<Option<&str>> = match options.accounts.is_empty() {
+            true => vec![None],
+            false => options.accounts.iter().map(|a| Some(a.as_str())).collect(),
+        };
+
+        let mut shortcuts = Vec::new();
+        for account in accounts {
+            let target = LaunchTarget { account: account.map(String::from), ..LaunchTarget::instance(&inst.folder_name) };
+            shortcuts.push(self.shortcut_for_target(inst, &target, None));
+            for server in options.servers.iter().filter(|s| !s.address.trim().is_empty()) {
+                shortcuts.push(self.server_shortcut_for(inst, server, account));
             }
+            for world in options.worlds.iter() {
+                shortcuts.push(self.world_shortcut_for(inst, world, account));
+            }
+        }
+        shortcuts
+    }
+
+    fn shortcut_for_target(&self, inst: &Instance, target: &LaunchTarget, label: Option<&str>) -> DesiredShortcut {
+        let exe_path_string = self.shortcut_exe_path();
+        let options = self.instance_options(&inst.folder_name);
+        let mut app_name = self.config.naming.app_name(inst);
+        if let Some(label) = label {
+            app_name = format!("{} — {}", app_name, label);
+        }
+        // Only needed to tell the shortcuts apart when there are several accounts.
+        if let Some(account) = target.account.as_ref().filter(|_| options.accounts.len() > 1) {
+            app_name = format!("{} ({})", app_name, account);
+        }
+        let mut launch_options = target.launch_options();
+        if exe_path_string != self.prism_exe_path() {
+            launch_options = format!("{} {}", LAUNCH_COMMAND, launch_options);
+        }
+        if !options.extra_launch_options.trim().is_empty() {
+            launch_options.push(' ');
+            launch_options.push_str(options.extra_launch_options.trim());
+        }
+
+        let mut tags = self.config.naming.tags(inst);
+        tags.extend(self.config.collections.collection_name(&inst.group));
+        tags.extend(options.extra_tags.iter().filter(|t| !t.is_empty()).cloned());
+
+        DesiredShortcut {
+            // These are the arguments that go into Shortcut::new() as well
+            app_name,
+            exe : exe_path_string,
+            shortcut_path : String::new(),
+            start_dir : String::from(self.config.prism_main_path.to_string_lossy()),
+            launch_options,
+            icon : options.icon.as_ref().map(|p| p.to_string_lossy().to_string()).unwrap_or_default(),
+            tags,
+            is_hidden : options.hidden,
+            allow_overlay : options.allow_overlay,
+            allow_desktop_config : options.allow_desktop_config,
+            open_vr : options.open_vr,
+            last_play_time : (self.playtime.last_played(&inst.folder_name).unwrap_or(0) / 1000) as u32,
+            explicit : options.explicit_fields(),
         }
     }
 
-    pub fn update_steam_shortcuts(&mut self) {
+    /// The options the user set for an instance, or the defaults.
+    pub fn instance_options(&self, folder_name: &str) -> InstanceOptions {
+        self.config.instance_options.get(folder_name).cloned().unwrap_or_default()
+    }
 
+    /// Build the shortcuts for every checked instance.
+    pub fn desired_shortcuts(&self) -> Vec<DesiredShortcut> {
         // Build desired shortcuts as owned and upsert by app_id.
         // We also re-number "order" later, so the `order` we put here is temporary.
-        
-        let exe_path_string = self.config.prism_main_path.to_string_lossy().to_string()
-            + "\\" + PRISMLAUNCHER_EXE_PATH;
-        let mut desired_shortcuts = Vec::new();
+        self.instances.iter()
+            .filter(|inst| inst.checked)
+            .flat_map(|inst| self.shortcuts_for(inst))
+            .collect()
+    }
 
-        for inst in self.instances.iter() {
-            if inst.checked {
-                let app_name = inst.folder_name.clone();
-                let launch_options = format!("-l \"{}\"", app_name);
-
-                desired_shortcuts.push( DesiredShortcut {
-                    // These are the arguments that go into Shortcut::new() as well
-                    app_name : app_name.clone(),
-                    exe : exe_path_string.clone(),
-                    shortcut_path : String::new(),
-                    start_dir : String::from(self.config.prism_main_path.to_string_lossy()),
-                    launch_options : launch_options,
-
-                    // TODO
-                    icon : String::new(),
-                    tags : vec![String::from("Installed"), String::from("Ready to play")],
-                });
+    /// Compare the instances against `shortcuts.vdf` and find orphaned Opal shortcuts.
+    pub fn update_sync_status(&mut self) {
+        let existing = match read_steam_shortcuts(self.files(), &self.config.steam_shortcuts_path) {
+            Ok(shortcuts) => shortcuts,
+            // No shortcuts file yet simply means nothing was exported.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                error!("Couldn't read shortcuts! {}", e);
+                Vec::new()
             }
+        };
+        let statuses: Vec<SyncStatus> = self.instances.iter()
+            .map(|inst| match_status(inst, &self.shortcut_for(inst), &existing))
+            .collect();
+        for (inst, status) in self.instances.iter_mut().zip(statuses) {
+            inst.status = status;
+        }
+        self.orphaned_shortcuts = orphaned_shortcuts(&existing, &self.config.prism_main_path.join(INSTANCES_DIR));
+    }
+
+    pub fn update_steam_shortcuts(&mut self) -> io::Result<()> {
+        let desired_shortcuts = self.desired_shortcuts();
+        let mut record = ExportRecord::begin(self.files(), &self.config.steam_shortcuts_path, &desired_shortcuts).inspect_err(|e| {
+            error!("Couldn't back up shortcuts.vdf, nothing was exported! {}", e);
+        })?;
+
+        let app_ids = write_steam_shortcuts(self.files(), &self.config.steam_shortcuts_path, desired_shortcuts, &self.config.merge_policy, self.config.shortcut_placement, self.last_plan()).inspect_err(|e| {
+            error!("Couldn't update shortcuts! {}", e);
+        })?;
+        for (planned, app_id) in record.plan.iter_mut().zip(app_ids) {
+            planned.app_id = app_id;
         }
 
-        match write_steam_shortcuts(&self.config.steam_shortcuts_path, desired_shortcuts) {
-            Ok(_) => return,
+        // The shortcuts are written already, so the extras aren't worth failing the export over.
+        match self.install_steam_artwork() {
+            Ok(created) => record.created_files = created,
+            Err(e) => error!("Couldn't copy artwork! {}", e),
+        }
+        if self.config.controller.enabled {
+            if let Err(e) = self.install_controller_templates() {
+                error!("Couldn't write controller layouts! {}", e);
+            }
+        }
+        if let Err(e) = self.update_compat_tools() {
+            error!("Couldn't set compatibility tools! {}", e);
+        }
+        if self.config.collections.enabled {
+            if let Err(e) = self.update_steam_collections() {
+                error!("Couldn't update Steam collections! {}", e);
+            }
+        }
+        if let Err(e) = self.data_dir().and_then(|dir| ExportHistory::record(dir, record)) {
+            error!("Couldn't save the export history, this export can't be undone! {}", e);
+        }
+        self.update_export_history();
+        Ok(())
+    }
+
+    pub fn update_export_history(&mut self) {
+        match self.data_dir().and_then(ExportHistory::load) {
+            Ok(history) => self.export_history = history,
             Err(e) => {
-                let msg = format!("\nCouldn't update shortcuts! {}", e);
-                error!("{}", &msg);
-                self.log_printout.push_str(&msg);
-                return;
+                error!("Couldn't read the export history! {}", e);
+            }
+        }
+    }
+
+    /// What the last export to the current `shortcuts.vdf` wrote, so re-exporting can tell
+    /// Opal's old values from the ones the user changed in Steam.
+    pub fn last_plan(&self) -> &[PlannedShortcut] {
+        self.export_history.last()
+            .filter(|record| record.shortcuts_path == self.config.steam_shortcuts_path)
+            .map(|record| record.plan.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Put `shortcuts.vdf` back the way it was before the last export, with Steam closed.
+    pub fn undo_last_export(&mut self) -> io::Result<ExportRecord> {
+        let result = self.while_steam_closed(|model| ExportHistory::undo_last(model.data_dir()?, model.files())).inspect_err(|e| {
+            error!("Couldn't undo the last export! {}", e);
+        });
+        self.update_export_history();
+        result
+    }
+
+    /// Instance folder -> app_ids of every Opal shortcut in `shortcuts.vdf` (the instance's
+    /// own and its server and world shortcuts). Read back from the file because app_ids may have been
+    /// kept from earlier exports.
+    pub fn exported_app_ids(&self) -> io::Result<HashMap<String, Vec<u32>>> {
+        Ok(self.exported_shortcuts()?.into_iter()
+            .map(|(folder, shortcuts)| (folder, shortcuts.iter().map(|s| s.app_id).collect()))
+            .collect())
+    }
+
+    /// Instance folder -> every Opal shortcut of the instance in `shortcuts.vdf`.
+    pub fn exported_shortcuts(&self) -> io::Result<HashMap<String, Vec<ShortcutOwned>>> {
+        let shortcuts = read_steam_shortcuts(self.files(), &self.config.steam_shortcuts_path)?;
+        let mut exported: HashMap<String, Vec<ShortcutOwned>> = HashMap::new();
+        for shortcut in shortcuts.into_iter() {
+            if let Some(folder) = launched_instance(&shortcut) {
+                exported.entry(folder).or_default().push(shortcut);
             }
         }
+        Ok(exported)
+    }
+
+    /// Copy the custom artwork of every checked instance into Steam's grid folder.
+    /// Returns the files that weren't there before.
+    pub fn install_steam_artwork(&self) -> io::Result<Vec<PathBuf>> {
+        let Some(grid_dir) = grid_dir(&self.config.steam_shortcuts_path) else { return Ok(Vec::new()) };
+        let app_ids = self.exported_app_ids()?;
+        let mut created = Vec::new();
+        for inst in self.instances.iter().filter(|i| i.checked) {
+            let options = self.instance_options(&inst.folder_name);
+            if options.artwork.is_empty() { continue; }
+            for app_id in app_ids.get(&inst.folder_name).into_iter().flatten() {
+                created.extend(install_artwork(self.files(), &grid_dir, *app_id, &options.artwork)?);
+            }
+        }
+        Ok(created)
+    }
+
+    /// The Steam Input template for an instance: the one set for it, or the detected one.
+    pub fn controller_layout(&self, inst: &Instance) -> ControllerLayout {
+        self.instance_options(&inst.folder_name).controller_layout.unwrap_or_else(|| detect_layout(&inst.path))
+    }
+
+    /// Give the shortcuts of every checked instance a Steam Input configuration.
+    pub fn install_controller_templates(&self) -> io::Result<()> {
+        let Some(config_dir) = controller_config_dir(&self.config.steam_shortcuts_path) else { return Ok(()) };
+        let exported = self.exported_shortcuts()?;
+        let mut layouts = Vec::new();
+        for inst in self.instances.iter().filter(|i| i.checked) {
+            let layout = self.controller_layout(inst);
+            layouts.extend(exported.get(&inst.folder_name).into_iter().flatten().map(|s| (s.app_name.clone(), layout)));
+        }
+        install_controller_templates(self.files(), &config_dir, &layouts, self.config.controller.overwrite)?;
+        Ok(())
+    }
+
+    /// The Steam Play tool for an instance's shortcuts.
+    pub fn compat_tool(&self, inst: &Instance) -> CompatTool {
+        self.instance_options(&inst.folder_name).compat_tool.unwrap_or_else(|| self.config.compat_tool.clone())
+    }
+
+    /// The hooks `opal launch` runs for the instance: its own, or else its group's.
+    pub fn hooks(&self, inst: &Instance) -> Hooks {
+        let own = self.instance_options(&inst.folder_name).hooks;
+        if !own.is_empty() { return own; }
+        self.config.launch.group_hooks.get(&inst.group).cloned().unwrap_or(own)
+    }
+
+    pub fn already_running(&self, inst: &Instance) -> AlreadyRunning {
+        self.instance_options(&inst.folder_name).already_running.unwrap_or(self.config.launch.already_running)
+    }
+
+    pub fn backup_settings(&self, inst: &Instance) -> BackupSettings {
+        self.instance_options(&inst.folder_name).backup.unwrap_or_else(|| self.config.launch.backup.clone())
+    }
+
+    /// Set the Steam Play tool of every checked instance's shortcuts in `config.vdf`.
+    pub fn update_compat_tools(&self) -> io::Result<()> {
+        let Some(path) = config_vdf_path(&self.config.steam_shortcuts_path) else { return Ok(()) };
+        let app_ids = self.exported_app_ids()?;
+        let mut tools = HashMap::new();
+        for inst in self.instances.iter().filter(|i| i.checked) {
+            let tool = self.compat_tool(inst);
+            for app_id in app_ids.get(&inst.folder_name).into_iter().flatten() {
+                tools.insert(*app_id, tool.clone());
+            }
+        }
+        write_compat_tools(self.files(), &path, &tools)
+    }
+
+    /// Mirror the Prism groups of every Opal shortcut into Steam's collections.
+    pub fn update_steam_collections(&self) -> io::Result<()> {
+        let Some(path) = cloud_storage_path(&self.config.steam_shortcuts_path) else { return Ok(()) };
+        let app_ids = self.exported_app_ids()?;
+        let collections = &self.config.collections;
+
+        // Every group gets an entry, so shortcuts that left a group are taken out of it.
+        let mut memberships: HashMap<String, Vec<u32>> = HashMap::new();
+        for inst in self.instances.iter() {
+            if let Some(name) = collections.collection_name(&inst.group) {
+                memberships.entry(name).or_default();
+            }
+        }
+
+        let opal_app_ids: HashSet<u32> = app_ids.values().flatten().copied().collect();
+        for (folder, ids) in app_ids.iter() {
+            let group = self.instances.iter().find(|i| &i.folder_name == folder).map(|i| i.group.as_str());
+            if let Some(name) = group.and_then(|g| collections.collection_name(g)) {
+                memberships.entry(name).or_default().extend(ids);
+            }
+        }
+
+        update_cloud_collections(self.files(), &path, &memberships, &opal_app_ids)
+    }
+
+    /// Close Steam, write the shortcuts and start Steam again. Used by both the GUI and the CLI.
+    pub fn export_to_steam(&mut self) -> io::Result<()> {
+        self.while_steam_closed(|model| model.update_steam_shortcuts())
+    }
+
+    /// Steam overwrites `shortcuts.vdf` when it exits, so anything that edits it
+    /// has to run with Steam closed. Steam is started again afterwards either way.
+    pub fn while_steam_closed<T>(&mut self, f: impl FnOnce(&mut Self) -> io::Result<T>) -> io::Result<T> {
+        if let Err(e) = self.platform.steam.stop(Duration::from_millis(1000)) {
+            error!("Failed to close Steam: {e}");
+        }
+
+        let result = f(self);
+        self.update_sync_status();
+
+        if let Err(e) = self.platform.steam.start() {
+            error!("Failed to start Steam: {e}");
+        }
+        if let Err(e) = self.platform.steam.wait_until_started(Duration::from_millis(1000)) {
+            error!("Failed to check if Steam started: {e}");
+        }
+
+        result
+    }
+
+    pub fn save_config(&mut self) {
+        if let Err(e) = self.config.save() {
+            error!("Couldn't save settings! {}", e);
+        }
     }
 }
 
@@ -92,25 +501,30 @@ pub struct App {
 }
 
 impl App {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
 
         // Register your tabs here. Adding tabs = add another `Box::new(MyPage { ... })`.
         let pages: Vec<Box<dyn TabPage>> = vec![
             Box::new(ExportPage::default()),
             Box::new(SettingsPage::default()),
+            Box::new(DoctorPage::default()),
+            Box::new(StatsPage::default()),
             Box::new(LogPage::default()),
         ];
 
+        let platform = Platform::default();
         let mut model = AppModel {
+            config: Config::load(platform.locator.as_ref(), platform.files.as_ref()),
+            platform,
             ..Default::default()
         };
         model.update_instances();
+        model.keep_exported_selected();
 
         Self {
             pages,
             active: 0,
-            model : model,
-            ..Default::default()
+            model,
         }
     }
 }
@@ -151,10 +565,15 @@ impl eframe::App for App {
 
             // Borrow the active page mutably to render its UI
             if let Some(page) = self.pages.get_mut(self.active) {
-                page.ui(ui, &mut self.model);
+                ui.push_id(page.id(), |ui| page.ui(ui, &mut self.model));
             } else {
                 ui.label("No page selected.");
             }
         });
     }
+
+    // Opal keeps its own config file (shared with the CLI), so eframe's storage is unused.
+    fn save(&mut self, _storage: &mut dyn eframe::Storage) {
+        self.model.save_config();
+    }
 }
\ No newline at end of file
@@ -0,0 +1,62 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use super::instance_options::Artwork;
+use super::platform::FileIo;
+
+const GRID_DIR : &str = "grid";
+
+/// Custom artwork lives next to `shortcuts.vdf`, in `config/grid`.
+pub fn grid_dir(shortcuts_path: &Path) -> Option<PathBuf> {
+    shortcuts_path.parent().map(|config| config.join(GRID_DIR))
+}
+
+/// Grid files are named after the app_id: `<id>.png` (wide capsule), `<id>p.png` (portrait),
+/// `<id>_hero.png`, `<id>_logo.png`, `<id>_icon.png` and `<id>.json` (logo position).
+pub fn is_artwork_for(file_name: &str, app_id: u32) -> bool {
+    let id = app_id.to_string();
+    let Some(rest) = file_name.strip_prefix(&id) else { return false };
+    rest.starts_with('.') || rest.starts_with("p.") || rest.starts_with('_')
+}
+
+/// Rename every grid file for `old_id` to `new_id`. Returns how many files were moved.
+pub fn move_grid_artwork(files: &dyn FileIo, grid_dir: &Path, old_id: u32, new_id: u32) -> io::Result<usize> {
+    let entries = match files.list(grid_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    let mut moved = 0;
+    for path in entries {
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else { continue };
+        if !is_artwork_for(&name, old_id) { continue; }
+        let new_name = format!("{}{}", new_id, &name[old_id.to_string().len()..]);
+        files.rename(&path, &grid_dir.join(new_name))?;
+        moved += 1;
+    }
+    Ok(moved)
+}
+
+/// Copy the chosen images into the grid folder under the names Steam expects for `app_id`.
+/// Returns the files that weren't there before, which undoing the export deletes again.
+pub fn install_artwork(files: &dyn FileIo, grid_dir: &Path, app_id: u32, artwork: &Artwork) -> io::Result<Vec<PathBuf>> {
+    let images = [
+        (&artwork.grid, "p"),
+        (&artwork.wide, ""),
+        (&artwork.hero, "_hero"),
+        (&artwork.logo, "_logo"),
+    ];
+
+    let mut created = Vec::new();
+    for (source, suffix) in images {
+        let Some(source) = source else { continue };
+        let extension = source.extension().and_then(|e| e.to_str()).unwrap_or("png");
+        files.create_dir_all(grid_dir)?;
+        let target = grid_dir.join(format!("{}{}.{}", app_id, suffix, extension));
+        let existed = files.exists(&target);
+        // The image is the user's own file, only the copy is Steam's.
+        files.write(&target, &std::fs::read(source)?)?;
+        if !existed { created.push(target); }
+    }
+    Ok(created)
+}
@@ -0,0 +1,153 @@
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use log::info;
+use serde::{Serialize, Deserialize};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+use super::rules::now_ms;
+use super::ui::format_date_time;
+use super::worlds::saves_dir;
+
+const BACKUPS_DIR : &str = "backups";
+const BACKUP_EXTENSION : &str = "zip";
+/// Minecraft's lock on an open world; useless in a backup.
+const SESSION_LOCK_FILE : &str = "session.lock";
+
+/// Zipping up an instance's worlds before `opal launch` starts the game.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    /// How many backups of an instance to keep. Older ones are deleted after each backup.
+    pub keep: usize,
+}
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self { enabled: false, keep: 5 }
+    }
+}
+
+/// One archive of an instance's `saves` folder.
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// Milliseconds since the epoch.
+    pub created: u64,
+    /// Bytes.
+    pub size: u64,
+    /// World folders in the archive.
+    pub worlds: Vec<String>,
+}
+
+/// Where the backups of an instance go: `<data dir>/backups/<instance folder>`.
+pub fn backups_dir(data_dir: &Path, folder: &str) -> PathBuf {
+    data_dir.join(BACKUPS_DIR).join(folder)
+}
+
+/// Zip the instance's `saves` folder into a new timestamped backup and delete the ones beyond
+/// `keep`. Returns `None` if the instance has no worlds yet.
+pub fn back_up_worlds(data_dir: &Path, instance_path: &Path, folder: &str, keep: usize) -> io::Result<Option<PathBuf>> {
+    let Some(saves) = saves_dir(instance_path) else { return Ok(None) };
+    if std::fs::read_dir(&saves)?.next().is_none() { return Ok(None) }
+    let dir = backups_dir(data_dir, folder);
+    std::fs::create_dir_all(&dir)?;
+
+    // Names only go down to the second, so backups made in the same second are numbered.
+    let stamp = format_timestamp(now_ms());
+    let mut path = dir.join(format!("{}.{}", stamp, BACKUP_EXTENSION));
+    for n in 2.. {
+        if !path.exists() { break; }
+        path = dir.join(format!("{}-{}.{}", stamp, n, BACKUP_EXTENSION));
+    }
+    // Written under another name first, so an interrupted backup doesn't look like one.
+    let partial = path.with_extension("zip.part");
+    let mut zip = ZipWriter::new(File::create(&partial)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    add_dir(&mut zip, &saves, "", options)?;
+    zip.finish().map_err(io::Error::other)?;
+    std::fs::rename(&partial, &path)?;
+    info!("Backed up the worlds of {} to {}", folder, path.display());
+
+    for old in list_backups(data_dir, folder).into_iter().skip(keep.max(1)) {
+        std::fs::remove_file(&old.path)?;
+    }
+    Ok(Some(path))
+}
+
+fn add_dir(zip: &mut ZipWriter<File>, dir: &Path, prefix: &str, options: SimpleFileOptions) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            zip.add_directory(name.as_str(), options).map_err(io::Error::other)?;
+            add_dir(zip, &entry.path(), &format!("{}/", name), options)?;
+        } else if entry.file_name() != SESSION_LOCK_FILE {
+            zip.start_file(name.as_str(), options).map_err(io::Error::other)?;
+            io::copy(&mut File::open(entry.path())?, zip)?;
+        }
+    }
+    Ok(())
+}
+
+/// The backups of an instance, newest first. Archives that can't be read are left out.
+pub fn list_backups(data_dir: &Path, folder: &str) -> Vec<Backup> {
+    let Ok(entries) = std::fs::read_dir(backups_dir(data_dir, folder)) else { return Vec::new() };
+    let mut backups: Vec<Backup> = entries.flatten()
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == BACKUP_EXTENSION))
+        .filter_map(|path| {
+            let metadata = std::fs::metadata(&path).ok()?;
+            let created = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64;
+            let archive = ZipArchive::new(File::open(&path).ok()?).ok()?;
+            let mut worlds: Vec<String> = archive.file_names()
+                .filter_map(|name| name.split_once('/').map(|(world, _)| world.to_string()))
+                .collect();
+            worlds.sort();
+            worlds.dedup();
+            Some(Backup { path, created, size: metadata.len(), worlds })
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created));
+    backups
+}
+
+/// Put a world from a backup back into the instance. A world that is there already is kept
+/// next to it, renamed, rather than overwritten.
+pub fn restore_world(backup: &Path, world: &str, instance_path: &Path) -> io::Result<()> {
+    let Some(saves) = saves_dir(instance_path) else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "The instance has no saves folder"));
+    };
+    let mut archive = ZipArchive::new(File::open(backup)?).map_err(io::Error::other)?;
+    let staging = saves.join(format!(".{}.restoring", world));
+    if staging.exists() { std::fs::remove_dir_all(&staging)?; }
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(io::Error::other)?;
+        // `enclosed_name` rejects paths that would end up outside the target folder.
+        let Some(name) = file.enclosed_name() else { continue };
+        let Ok(relative) = name.strip_prefix(world) else { continue };
+        let target = staging.join(relative);
+        if file.is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            if let Some(parent) = target.parent() { std::fs::create_dir_all(parent)?; }
+            io::copy(&mut file, &mut File::create(&target)?)?;
+        }
+    }
+    if !staging.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} isn't in this backup", world)));
+    }
+
+    let target = saves.join(world);
+    if target.exists() {
+        std::fs::rename(&target, saves.join(format!("{} (before restore {})", world, format_timestamp(now_ms()))))?;
+    }
+    std::fs::rename(&staging, &target)?;
+    info!("Restored {} from {}", world, backup.display());
+    Ok(())
+}
+
+/// `2024-05-01_18-30-00` in UTC, which sorts and works as a file name everywhere.
+fn format_timestamp(ms: u64) -> String {
+    format_date_time(ms).replace(' ', "_").replace(':', "-")
+}
@@ -0,0 +1,186 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use directories::BaseDirs;
+use regex::Regex;
+use serde_json::json;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+use super::app::{AppModel, APP_NAME};
+use super::compat::config_vdf_path;
+use super::doctor::run_checks;
+use super::instances::default_prism_path;
+use super::logger;
+use super::settings_page::Config;
+use super::steam::{read_steam_shortcuts, steam_dir_from_shortcuts};
+use super::sync::launch_target;
+use super::ui::format_date_time;
+
+const FLATPAK_APP_ID : &str = "org.prismlauncher.PrismLauncher";
+/// Prism runs in portable mode when this file is next to its executable.
+const PORTABLE_MARKER : &str = "portable.txt";
+const OS_RELEASE_FILE : &str = "/etc/os-release";
+
+/// Replaces what identifies the user in everything that goes into a bundle: the home folder
+/// (which usually contains the user name), the Steam user ID and PrismLauncher account names.
+struct Anonymizer {
+    replacements: Vec<(Regex, String)>,
+}
+impl Anonymizer {
+    fn new(model: &AppModel) -> Self {
+        let mut replacements = Vec::new();
+        if let Some(home) = BaseDirs::new().map(|dirs| dirs.home_dir().to_string_lossy().to_string()) {
+            // Also in JSON, where Windows' backslashes are escaped.
+            for form in [home.replace('\\', "\\\\"), home] {
+                if form.len() > 1 {
+                    replacements.push((Regex::new(&regex::escape(&form)).unwrap(), String::from("~")));
+                }
+            }
+        }
+        let mut user_ids = vec![model.config.user_id.to_string()];
+        if let Some(id) = model.config.steam_shortcuts_path.parent().and_then(Path::parent).and_then(Path::file_name) {
+            user_ids.push(id.to_string_lossy().to_string());
+        }
+        for id in user_ids {
+            replacements.push((Regex::new(&format!(r"\b{}\b", regex::escape(&id))).unwrap(), String::from("<steam user>")));
+        }
+        let mut accounts: Vec<&String> = model.accounts.iter()
+            .chain(model.config.instance_options.values().flat_map(|o| o.accounts.iter()))
+            .collect();
+        accounts.sort();
+        accounts.dedup();
+        for (i, account) in accounts.into_iter().enumerate() {
+            replacements.push((Regex::new(&format!(r"\b{}\b", regex::escape(account))).unwrap(), format!("<account {}>", i + 1)));
+        }
+        Self { replacements }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        self.replacements.iter().fold(text.to_string(), |text, (pattern, replacement)| {
+            pattern.replace_all(&text, regex::NoExpand(replacement)).to_string()
+        })
+    }
+}
+
+/// Zip up what's needed to look into a bug report, with the user's paths and accounts replaced.
+pub fn create_diagnostic_bundle(model: &AppModel, path: &Path) -> io::Result<()> {
+    let anonymizer = Anonymizer::new(model);
+    // Numbers can't be replaced in the JSON text without breaking it.
+    let config = Config { user_id: 0, ..model.config.clone() };
+    let files = [
+        ("environment.txt", environment(model)),
+        ("doctor.txt", doctor(model)),
+        ("config.json", serde_json::to_string_pretty(&config).map_err(io::Error::other)?),
+        ("instances.json", instances(model)?),
+        ("shortcuts.json", shortcuts(model)?),
+        ("opal.log", logger::entries().iter().map(|e| e.format()).collect::<Vec<_>>().join("\n")),
+    ];
+
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, contents) in files {
+        zip.start_file(name, options).map_err(io::Error::other)?;
+        zip.write_all(anonymizer.apply(&contents).as_bytes())?;
+    }
+    zip.finish().map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// How PrismLauncher was installed, going by where its folder is.
+fn prism_install_kind(prism_path: &Path) -> &'static str {
+    if prism_path.components().any(|c| c.as_os_str() == FLATPAK_APP_ID) { "Flatpak" }
+    else if prism_path.join(PORTABLE_MARKER).is_file() { "Portable" }
+    else if default_prism_path().is_ok_and(|default| default == prism_path) { "Default location" }
+    else { "Custom location" }
+}
+
+fn environment(model: &AppModel) -> String {
+    let config = &model.config;
+    let exists = |path: &Path| if path.exists() { "found" } else { "missing" };
+    let mut lines = vec![
+        format!("{} {}", APP_NAME, env!("CARGO_PKG_VERSION")),
+        format!("OS: {} ({})", std::env::consts::OS, std::env::consts::ARCH),
+    ];
+    if let Ok(os_release) = std::fs::read_to_string(OS_RELEASE_FILE) {
+        if let Some(name) = os_release.lines().find_map(|l| l.strip_prefix("PRETTY_NAME=")) {
+            lines.push(format!("Distribution: {}", name.trim_matches('"')));
+        }
+    }
+    lines.push(format!("Opal data folder: {}", model.data_dir().map(|d| d.display().to_string()).unwrap_or_default()));
+
+    lines.push(String::new());
+    lines.push(format!("PrismLauncher: {} ({})", config.prism_main_path.display(), prism_install_kind(&config.prism_main_path)));
+    lines.push(format!("PrismLauncher executable: {} ({})", model.prism_exe_path(), exists(Path::new(&model.prism_exe_path()))));
+    lines.push(format!("Instance groups: {} ({})", model.instances_json_path().display(), exists(&model.instances_json_path())));
+    lines.push(format!("Instances: {}, accounts: {}", model.instances.len(), model.accounts.len()));
+
+    lines.push(String::new());
+    match model.platform.locator.steam_dir() {
+        Ok(steam) => lines.push(format!("Steam: {}", steam.display())),
+        Err(e) => lines.push(format!("Steam: not found ({})", e)),
+    }
+    lines.push(format!("Steam running: {}", model.platform.steam.is_running()));
+    lines.push(format!("Shortcuts: {} ({})", config.steam_shortcuts_path.display(), exists(&config.steam_shortcuts_path)));
+    if let Some(steam_dir) = steam_dir_from_shortcuts(&config.steam_shortcuts_path) {
+        lines.push(format!("Steam folder from the shortcuts path: {}", steam_dir.display()));
+    }
+    if let Some(path) = config_vdf_path(&config.steam_shortcuts_path) {
+        lines.push(format!("config.vdf: {} ({})", path.display(), exists(&path)));
+    }
+    lines.push(format!("Steam Play tools: {}", model.compat_tools.join(", ")));
+    lines.join("\n")
+}
+
+fn doctor(model: &AppModel) -> String {
+    run_checks(model).iter()
+        .map(|f| format!("[{:?}] {}: {}", f.severity, f.check, f.message))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn instances(model: &AppModel) -> io::Result<String> {
+    let instances: Vec<_> = model.instances.iter()
+        .map(|inst| json!({
+            "folder": inst.folder_name,
+            "name": inst.display_name(),
+            "group": inst.group,
+            "path": inst.path,
+            "minecraft": inst.mc_version,
+            "loader": inst.loader.name(),
+            "loader_version": inst.loader_version,
+            "last_launch": inst.last_launch,
+            "total_time_played": inst.total_time_played,
+            "selected": inst.checked,
+            "status": inst.status.label(),
+        }))
+        .collect();
+    serde_json::to_string_pretty(&instances).map_err(io::Error::other)
+}
+
+/// The shortcuts Opal made. Everything else in `shortcuts.vdf` is the user's business.
+fn shortcuts(model: &AppModel) -> io::Result<String> {
+    let shortcuts = match read_steam_shortcuts(model.files(), &model.config.steam_shortcuts_path) {
+        Ok(shortcuts) => shortcuts,
+        Err(e) => return Ok(json!({ "error": e.to_string() }).to_string()),
+    };
+    let ours: Vec<_> = shortcuts.iter()
+        .filter(|s| launch_target(s).is_some())
+        .map(|s| json!({
+            "app_id": s.app_id,
+            "app_name": s.app_name,
+            "exe": s.exe,
+            "start_dir": s.start_dir,
+            "icon": s.icon,
+            "launch_options": s.launch_options,
+            "is_hidden": s.is_hidden,
+            "last_play_time": s.last_play_time,
+            "tags": s.tags,
+            "order": s.order,
+        }))
+        .collect();
+    serde_json::to_string_pretty(&ours).map_err(io::Error::other)
+}
+
+/// `opal-diagnostics-2024-05-01_18-30-00.zip`
+pub fn bundle_file_name(now_ms: u64) -> String {
+    format!("opal-diagnostics-{}.zip", format_date_time(now_ms).replace(' ', "_").replace(':', "-"))
+}
@@ -0,0 +1,261 @@
+use log::info;
+use super::app::{AppModel, APP_NAME};
+use super::backup::back_up_worlds;
+use super::crash::capture_crash;
+use super::hooks::{run_hook, HookFailure, HookStage, Hooks};
+use super::launch::{focus_process, instance_argument, record_session, run_session, running_game, AlreadyRunning};
+use super::ui::{format_date_time, format_play_time};
+use super::doctor::{apply_fixes, run_checks, Finding, Fix, Severity};
+use super::migrate::{migrate_shortcuts, pending_migrations};
+use super::platform::Platform;
+use super::settings_page::Config;
+
+const USAGE : &str = "\
+Usage: opal [COMMAND]
+
+Without a command, the graphical interface is opened.
+
+Commands:
+  list                 List instances and whether the selection rules pick them
+  export [--dry-run]   Export the instances picked by the selection rules to Steam
+  doctor [--fix]       Check PrismLauncher, Steam and the exported shortcuts
+  repair [--dry-run] [--new-app-ids]
+                       Point existing shortcuts at the configured PrismLauncher after it moved
+  undo [--dry-run]     Put the shortcuts back the way they were before the last export
+  launch <instance> [PrismLauncher arguments]
+                       Start an instance through PrismLauncher and record its play time
+                       (also accepts `-l <instance>`, which is what exported shortcuts use)
+  help                 Show this message";
+
+/// Run a command-line invocation and return the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let platform = Platform::default();
+    let mut model = AppModel {
+        config: Config::load(platform.locator.as_ref(), platform.files.as_ref()),
+        platform,
+        ..Default::default()
+    };
+
+    match args.first().map(String::as_str) {
+        Some("list") => list(&mut model),
+        Some("export") => export(&mut model, args[1..].iter().any(|a| a == "--dry-run")),
+        Some("doctor") => doctor(&mut model, args[1..].iter().any(|a| a == "--fix")),
+        Some("repair") => repair(&mut model,
+            args[1..].iter().any(|a| a == "--dry-run"),
+            !args[1..].iter().any(|a| a == "--new-app-ids")),
+        Some("undo") => undo(&mut model, args[1..].iter().any(|a| a == "--dry-run")),
+        Some("launch") => launch(&mut model, &args[1..]),
+        Some("help") | Some("--help") | Some("-h") => {
+            println!("{} {}\n\n{}", APP_NAME, env!("CARGO_PKG_VERSION"), USAGE);
+            0
+        }
+        Some(other) => {
+            eprintln!("Unknown command `{}`.\n\n{}", other, USAGE);
+            2
+        }
+        None => 0,
+    }
+}
+
+fn list(model: &mut AppModel) -> i32 {
+    if !model.update_instances() { return 1; }
+
+    // `update_instances` selected them by the rules, which is what `export` exports.
+    for inst in model.instances.iter() {
+        println!("[{}] {} ({}, {} {}) - {}",
+            if inst.checked { "x" } else { " " },
+            inst.display_name(),
+            if inst.group.is_empty() { "-" } else { &inst.group },
+            inst.mc_version.as_deref().unwrap_or("?"),
+            inst.loader.name(),
+            inst.status.label(),
+        );
+    }
+    for shortcut in model.orphaned_shortcuts.iter() {
+        println!("[!] {} - Orphaned shortcut", shortcut.app_name);
+    }
+    0
+}
+
+fn export(model: &mut AppModel, dry_run: bool) -> i32 {
+    if model.config.selection_rules.is_empty() {
+        eprintln!("No selection rules are configured. Add some on the Export page first.");
+        return 1;
+    }
+    if !model.update_instances() { return 1; }
+
+    let desired = model.desired_shortcuts();
+    for shortcut in desired.iter() {
+        println!("{} {}", if dry_run { "Would export" } else { "Exporting" }, shortcut.app_name);
+    }
+    if dry_run { return 0; }
+
+    match model.export_to_steam() {
+        Ok(()) => 0,
+        Err(_) => 1,
+    }
+}
+
+fn doctor(model: &mut AppModel, fix: bool) -> i32 {
+    // A broken instance list is one of the findings, not a reason to stop.
+    model.update_instances();
+
+    let findings = run_checks(model);
+    print_findings(&findings);
+
+    let fixes: Vec<Fix> = findings.iter().filter_map(|f| f.fix.clone()).collect();
+    if fix && !fixes.is_empty() {
+        println!("\nApplying {} fixes...", fixes.len());
+        if let Err(e) = model.while_steam_closed(|model| apply_fixes(model, &fixes)) {
+            eprintln!("Couldn't apply fixes! {}", e);
+            return 1;
+        }
+        let findings = run_checks(model);
+        println!();
+        print_findings(&findings);
+        return if findings.iter().any(|f| f.severity == Severity::Error) { 1 } else { 0 };
+    } else if !fixes.is_empty() {
+        println!("\n{} problems can be fixed automatically with `opal doctor --fix`.", fixes.len());
+    }
+
+    if findings.iter().any(|f| f.severity == Severity::Error) { 1 } else { 0 }
+}
+
+fn repair(model: &mut AppModel, dry_run: bool, keep_app_ids: bool) -> i32 {
+    let pending = match pending_migrations(model, keep_app_ids) {
+        Ok(pending) => pending,
+        Err(e) => {
+            eprintln!("Couldn't read shortcuts! {}", e);
+            return 1;
+        }
+    };
+    if pending.is_empty() {
+        println!("All shortcuts already point to {}.", model.prism_exe_path());
+        return 0;
+    }
+    for migration in pending.iter() {
+        println!("{} {} ({} -> {})", if dry_run { "Would repair" } else { "Repairing" },
+            migration.app_name, migration.old_exe, model.prism_exe_path());
+    }
+    if dry_run { return 0; }
+
+    match model.while_steam_closed(|model| migrate_shortcuts(model, keep_app_ids)) {
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("Couldn't repair shortcuts! {}", e);
+            1
+        }
+    }
+}
+
+fn undo(model: &mut AppModel, dry_run: bool) -> i32 {
+    model.update_export_history();
+    let Some(record) = model.export_history.last() else {
+        println!("There is no export to undo.");
+        return 0;
+    };
+    println!("{} the export from {} of:", if dry_run { "Would undo" } else { "Undoing" }, format_date_time(record.time));
+    for shortcut in record.plan.iter() {
+        println!("  {}", shortcut.app_name);
+    }
+    if !record.created_files.is_empty() {
+        println!("{} the {} artwork files it added.", if dry_run { "Would delete" } else { "Deleting" }, record.created_files.len());
+    }
+    if dry_run { return 0; }
+
+    // Failures are logged, which prints them to stderr.
+    match model.undo_last_export() {
+        Ok(_) => 0,
+        Err(_) => 1,
+    }
+}
+
+fn print_findings(findings: &[Finding]) {
+    for finding in findings {
+        let tag = match finding.severity {
+            Severity::Ok => "ok",
+            Severity::Warning => "warn",
+            Severity::Error => "FAIL",
+        };
+        let fix = finding.fix.as_ref().map(|f| format!(" [fix: {}]", f.label())).unwrap_or_default();
+        println!("[{:>4}] {}: {}{}", tag, finding.check, finding.message, fix);
+    }
+}
+
+fn launch(model: &mut AppModel, args: &[String]) -> i32 {
+    // `opal launch ATM9 ...` is short for `opal launch -l ATM9 ...`.
+    let prism_args: Vec<String> = match args.first() {
+        Some(first) if !first.starts_with('-') => [String::from("-l")].into_iter().chain(args.iter().cloned()).collect(),
+        _ => args.to_vec(),
+    };
+    let Some(folder) = instance_argument(&prism_args) else {
+        eprintln!("Which instance? Usage: opal launch <instance>");
+        return 2;
+    };
+    if !model.update_instances() { return 1; }
+    let Some(inst) = model.instances.iter().find(|i| i.folder_name == folder) else {
+        eprintln!("There is no instance called {}.", folder);
+        return 1;
+    };
+
+    if let Some(pid) = running_game(&inst.path) {
+        let focused = model.already_running(inst) == AlreadyRunning::Focus && focus_process(pid);
+        if focused { return 0; }
+        // Launched from Steam, nobody would see a message on the terminal.
+        let msg = format!("{} is already running.", inst.display_name());
+        eprintln!("{}", msg);
+        rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Warning)
+            .set_title(APP_NAME)
+            .set_description(msg)
+            .show();
+        return 1;
+    }
+
+    let backup = model.backup_settings(inst);
+    if backup.enabled {
+        // Better to play without a fresh backup than not at all; the older ones are still there.
+        if let Err(e) = model.data_dir().and_then(|dir| back_up_worlds(dir, &inst.path, &inst.folder_name, backup.keep)) {
+            eprintln!("Warning: couldn't back up the worlds of {}: {}", folder, e);
+        }
+    }
+
+    let hooks = model.hooks(inst);
+    if let Err(e) = run_hook(&hooks, HookStage::PreLaunch, inst, None) {
+        if hooks.on_failure == HookFailure::Abort {
+            eprintln!("Not launching {}: {}", folder, e);
+            return 1;
+        }
+        report_hook_failure(&hooks, e);
+    }
+
+    let (session, exit) = match run_session(model, inst, &prism_args) {
+        Ok(played) => played,
+        Err(e) => {
+            eprintln!("Couldn't launch {}: {}", folder, e);
+            return 1;
+        }
+    };
+    println!("Played {} for {}.", inst.display_name(), format_play_time(session.duration_secs));
+    match model.data_dir().and_then(|dir| capture_crash(dir, &inst.path, &session, exit)) {
+        Ok(Some(crash)) => eprintln!("{} crashed: {}\nThe log is in {}.", inst.display_name(),
+            crash.exception.as_deref().or(crash.exit.as_deref()).unwrap_or("no exception found"), crash.archive.display()),
+        Ok(None) => {}
+        Err(e) => eprintln!("Couldn't archive the game's log: {}", e),
+    }
+    if let Err(e) = run_hook(&hooks, HookStage::PostExit, inst, Some(session.duration_secs)) {
+        report_hook_failure(&hooks, e);
+    }
+    if let Err(e) = record_session(model, inst, &session) {
+        eprintln!("Couldn't record the session: {}", e);
+        return 1;
+    }
+    0
+}
+
+fn report_hook_failure(hooks: &Hooks, e: std::io::Error) {
+    match hooks.on_failure {
+        HookFailure::Ignore => info!("Ignoring: {}", e),
+        _ => eprintln!("Warning: {}", e),
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use super::platform::FileIo;
+use super::rules::now_ms;
+
+const CLOUD_STORAGE_DIR : &str = "cloudstorage";
+const CLOUD_STORAGE_FILE : &str = "cloud-storage-namespace-1.json";
+const BACKUP_EXTENSION : &str = "json.bak";
+const COLLECTION_KEY_PREFIX : &str = "user-collections.";
+const UNGROUPED_COLLECTION : &str = "Ungrouped";
+
+/// How Prism groups become Steam collections.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CollectionSettings {
+    pub enabled: bool,
+    /// Put in front of every collection name, e.g. "Minecraft: ".
+    pub prefix: String,
+    /// Leave instances that aren't in a Prism group out of every collection.
+    pub skip_ungrouped: bool,
+}
+impl Default for CollectionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            prefix: String::new(),
+            skip_ungrouped: true,
+        }
+    }
+}
+impl CollectionSettings {
+    /// The collection an instance in `group` belongs to, if any.
+    pub fn collection_name(&self, group: &str) -> Option<String> {
+        if !self.enabled { return None; }
+        match group {
+            "" if self.skip_ungrouped => None,
+            "" => Some(format!("{}{}", self.prefix, UNGROUPED_COLLECTION)),
+            group => Some(format!("{}{}", self.prefix, group)),
+        }
+    }
+}
+
+/// Modern Steam clients keep collections in the user's cloud storage namespace,
+/// next to `shortcuts.vdf`: `config/cloudstorage/cloud-storage-namespace-1.json`.
+pub fn cloud_storage_path(shortcuts_path: &Path) -> Option<PathBuf> {
+    shortcuts_path.parent().map(|config| config.join(CLOUD_STORAGE_DIR).join(CLOUD_STORAGE_FILE))
+}
+
+/// A collection as stored (JSON-encoded) in an entry's `value`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Collection {
+    id: String,
+    name: String,
+    #[serde(default)]
+    added: Vec<u32>,
+    #[serde(default)]
+    removed: Vec<u32>,
+}
+
+/// Put every Opal shortcut into the collection for its group, and take it out of the
+/// other group collections. `memberships` maps the name of every group collection (even
+/// empty ones) to its app_ids, and `opal_app_ids` lists every shortcut Opal owns.
+/// Collections that aren't named after a group, and apps Opal didn't make, are left alone.
+/// The previous file is kept as a `.json.bak`.
+pub fn update_cloud_collections(files: &dyn FileIo, path: &Path, memberships: &HashMap<String, Vec<u32>>, opal_app_ids: &HashSet<u32>) -> io::Result<()> {
+    let mut entries: Vec<(String, Value)> = match files.read(path) {
+        Ok(json) => {
+            files.write(&path.with_extension(BACKUP_EXTENSION), &json)?;
+            serde_json::from_slice(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    let mut version = entries.iter()
+        .filter_map(|(_, entry)| entry["version"].as_str()?.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0);
+    let timestamp = now_ms() / 1000;
+    let mut seen = HashSet::new();
+
+    for (key, entry) in entries.iter_mut() {
+        if !key.starts_with(COLLECTION_KEY_PREFIX) || entry["is_deleted"].as_bool() == Some(true) { continue; }
+        let Some(mut collection) = entry["value"].as_str().and_then(|v| serde_json::from_str::<Collection>(v).ok()) else { continue };
+
+        let Some(wanted) = memberships.get(&collection.name) else { continue };
+        let before = collection.added.clone();
+        collection.added.retain(|id| !opal_app_ids.contains(id) || wanted.contains(id));
+        for id in wanted {
+            if !collection.added.contains(id) { collection.added.push(*id); }
+            collection.removed.retain(|removed| removed != id);
+        }
+        seen.insert(collection.name.clone());
+
+        if collection.added != before {
+            version += 1;
+            entry["value"] = Value::String(serde_json::to_string(&collection)?);
+            entry["timestamp"] = json!(timestamp);
+            entry["version"] = json!(version.to_string());
+        }
+    }
+
+    let mut new_names: Vec<&String> = memberships.iter()
+        .filter(|(name, ids)| !ids.is_empty() && !seen.contains(*name))
+        .map(|(name, _)| name)
+        .collect();
+    new_names.sort();
+    for name in new_names {
+        version += 1;
+        let collection = Collection {
+            id: collection_id(name),
+            name: name.clone(),
+            added: memberships[name].clone(),
+            removed: Vec::new(),
+        };
+        let key = format!("{}{}", COLLECTION_KEY_PREFIX, collection.id);
+        let entry = json!({
+            "key": key,
+            "timestamp": timestamp,
+            "value": serde_json::to_string(&collection)?,
+            "version": version.to_string(),
+            "conflictResolutionMethod": "custom",
+            "strMethodId": "union-collections",
+        });
+        // A collection deleted in Steam keeps its key, marked `is_deleted`. Bring it back
+        // rather than adding a second entry with the same key.
+        match entries.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => *existing = entry,
+            None => entries.push((key, entry)),
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        files.create_dir_all(parent)?;
+    }
+    files.write(path, serde_json::to_string(&entries)?.as_bytes())
+}
+
+/// Steam ids look like `uc-0123456789ab`. Derive ours from the name (FNV-1a) so
+/// exporting twice finds the same collection instead of making a new one.
+fn collection_id(name: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("uc-{:012x}", hash & 0xffff_ffff_ffff)
+}
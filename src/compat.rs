@@ -0,0 +1,140 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use keyvalues_parser::{Obj, Value, Vdf};
+use serde::{Serialize, Deserialize};
+use super::platform::FileIo;
+use super::steam::steam_dir_from_shortcuts;
+
+const CONFIG_VDF : &str = "config/config.vdf";
+const BACKUP_EXTENSION : &str = "vdf.bak";
+const COMPAT_TOOLS_DIR : &str = "compatibilitytools.d";
+/// The manifest every custom tool in `compatibilitytools.d` has.
+const COMPAT_TOOL_MANIFEST : &str = "compatibilitytool.vdf";
+/// Where `CompatToolMapping` lives below the root `InstallConfigStore`.
+const MAPPING_PATH : [&str; 4] = ["Software", "Valve", "Steam", "CompatToolMapping"];
+/// What Steam writes when a tool is forced from a game's properties.
+const USER_PRIORITY : &str = "250";
+
+/// The Steam Play compatibility tool for a shortcut. Only matters on Linux, where Steam
+/// sometimes attaches Proton to non-Steam shortcuts, which breaks the native PrismLauncher.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub enum CompatTool {
+    /// Leave whatever Steam has.
+    #[default]
+    Unchanged,
+    /// Run PrismLauncher natively.
+    NoTool,
+    /// A tool by its internal name, e.g. `proton_experimental`.
+    Tool(String),
+}
+impl CompatTool {
+    pub fn label(&self) -> String {
+        match self {
+            CompatTool::Unchanged => String::from("Leave as is"),
+            CompatTool::NoTool => String::from("No compatibility tool"),
+            CompatTool::Tool(name) => name.clone(),
+        }
+    }
+}
+
+/// `<Steam>/config/config.vdf`, found from the shortcuts path.
+pub fn config_vdf_path(shortcuts_path: &Path) -> Option<PathBuf> {
+    steam_dir_from_shortcuts(shortcuts_path).map(|steam| steam.join(CONFIG_VDF))
+}
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// Keys in config.vdf aren't consistently capitalised (`Valve` vs `valve`).
+fn find_key<'a>(obj: &Obj<'a>, key: &str) -> Option<Cow<'a, str>> {
+    obj.keys().find(|k| k.eq_ignore_ascii_case(key)).cloned()
+}
+
+fn child<'o>(obj: &'o Obj<'o>, key: &str) -> Option<&'o Obj<'o>> {
+    obj.get(find_key(obj, key)?.as_ref())?.first()?.get_obj()
+}
+
+/// The object at `key`, created if it doesn't exist.
+fn child_mut<'o, 'a>(obj: &'o mut Obj<'a>, key: &str) -> io::Result<&'o mut Obj<'a>> {
+    let key = find_key(obj, key).unwrap_or_else(|| Cow::Owned(key.to_string()));
+    let values = obj.entry(key).or_default();
+    if values.is_empty() {
+        values.push(Value::Obj(Obj::new()));
+    }
+    values[0].get_mut_obj().ok_or_else(|| invalid("config.vdf has a value where a section was expected"))
+}
+
+/// app_id -> tool name of every mapping in `config.vdf`. Tools with an empty name are left out.
+pub fn read_compat_tools(files: &dyn FileIo, path: &Path) -> io::Result<HashMap<u32, String>> {
+    let text = String::from_utf8(files.read(path)?).map_err(invalid)?;
+    let vdf = Vdf::parse(&text).map_err(invalid)?;
+    let Some(root) = vdf.value.get_obj() else { return Ok(HashMap::new()) };
+    let Some(mapping) = MAPPING_PATH.iter().try_fold(root, |obj, key| child(obj, key)) else { return Ok(HashMap::new()) };
+
+    Ok(mapping.iter()
+        .filter_map(|(app_id, values)| {
+            let name = child_str(values.first()?.get_obj()?, "name")?;
+            if name.is_empty() { return None; }
+            Some((app_id.parse().ok()?, name.to_string()))
+        })
+        .collect())
+}
+
+fn child_str<'o>(obj: &'o Obj, key: &str) -> Option<&'o str> {
+    obj.get(find_key(obj, key)?.as_ref())?.first()?.get_str()
+}
+
+/// Apply `tools` to the mapping in `config.vdf`. `Unchanged` entries are skipped. The previous
+/// file is kept as a `.vdf.bak`. The caller is responsible for closing Steam first.
+pub fn write_compat_tools(files: &dyn FileIo, path: &Path, tools: &HashMap<u32, CompatTool>) -> io::Result<()> {
+    if tools.values().all(|t| *t == CompatTool::Unchanged) { return Ok(()); }
+
+    let text = String::from_utf8(files.read(path)?).map_err(invalid)?;
+    let mut vdf = Vdf::parse(&text).map_err(invalid)?;
+    let root = vdf.value.get_mut_obj().ok_or_else(|| invalid("config.vdf has no root section"))?;
+    let mut mapping = root;
+    for key in MAPPING_PATH {
+        mapping = child_mut(mapping, key)?;
+    }
+
+    for (app_id, tool) in tools {
+        let key = Cow::Owned(app_id.to_string());
+        match tool {
+            CompatTool::Unchanged => {}
+            CompatTool::NoTool => { mapping.remove(&key); }
+            CompatTool::Tool(name) => {
+                let mut entry = Obj::new();
+                for (k, v) in [("name", name.as_str()), ("config", ""), ("priority", USER_PRIORITY)] {
+                    entry.insert(Cow::Borrowed(k), vec![Value::Str(Cow::Owned(v.to_string()))]);
+                }
+                mapping.insert(key, vec![Value::Obj(entry)]);
+            }
+        }
+    }
+
+    files.write(&path.with_extension(BACKUP_EXTENSION), text.as_bytes())?;
+    files.write(path, vdf.to_string().as_bytes())
+}
+
+/// Names of the tools the user can pick: custom ones in `compatibilitytools.d` and every tool
+/// that is already mapped to some app.
+pub fn available_compat_tools(files: &dyn FileIo, shortcuts_path: &Path) -> Vec<String> {
+    let mut tools: Vec<String> = Vec::new();
+    if let Some(steam) = steam_dir_from_shortcuts(shortcuts_path) {
+        if let Ok(entries) = files.list(&steam.join(COMPAT_TOOLS_DIR)) {
+            // Every tool is a folder with a `compatibilitytool.vdf`.
+            tools.extend(entries.iter()
+                .filter(|dir| files.exists(&dir.join(COMPAT_TOOL_MANIFEST)))
+                .filter_map(|dir| Some(dir.file_name()?.to_string_lossy().to_string())));
+        }
+    }
+    if let Some(mapped) = config_vdf_path(shortcuts_path).and_then(|p| read_compat_tools(files, &p).ok()) {
+        tools.extend(mapped.into_values());
+    }
+    tools.sort();
+    tools.dedup();
+    tools
+}
@@ -0,0 +1,171 @@
+use std::borrow::Cow;
+use std::io;
+use std::path::{Path, PathBuf};
+use keyvalues_parser::{Obj, Value, Vdf};
+use serde::{Serialize, Deserialize};
+use super::platform::FileIo;
+use super::servers::game_dir;
+use super::steam::steam_dir_from_shortcuts;
+
+const MODS_DIR : &str = "mods";
+const CONTROLLER_CONFIGS_DIR : &str = "steamapps/common/Steam Controller Configs";
+/// The controller the templates are made for, the Steam Deck's built-in one.
+const TEMPLATE_CONTROLLER : &str = "controller_neptune";
+/// Every controller Steam keeps a configuration per app for. The templates are copied for
+/// each; Steam ignores bindings for inputs a controller doesn't have, like trackpads.
+const CONTROLLER_TYPES : [&str; 7] = [
+    "controller_neptune", "controller_steamcontroller_gordon", "controller_xboxone", "controller_xbox360",
+    "controller_ps4", "controller_ps5", "controller_switch_pro",
+];
+/// The root of `configset_<controller>.vdf`, which says which configuration each app uses.
+const CONFIG_SET_ROOT : &str = "controller_config";
+/// The config set entry that makes Steam use the configuration saved in the app's folder.
+const AUTOSAVE_KEY : &str = "autosave";
+
+/// Which Steam Input template an instance's shortcuts get.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControllerLayout {
+    /// Keyboard and mouse bindings for vanilla Minecraft.
+    KeyboardMouse,
+    /// A plain gamepad, for instances where a mod handles the controller.
+    Controlify,
+    MidnightControls,
+}
+impl ControllerLayout {
+    pub const ALL : [ControllerLayout; 3] = [
+        ControllerLayout::KeyboardMouse, ControllerLayout::Controlify, ControllerLayout::MidnightControls,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ControllerLayout::KeyboardMouse => "Keyboard and mouse",
+            ControllerLayout::Controlify => "Controlify",
+            ControllerLayout::MidnightControls => "MidnightControls",
+        }
+    }
+
+    fn template(&self) -> &'static str {
+        match self {
+            ControllerLayout::KeyboardMouse => include_str!("../assets/controller/keyboard_mouse.vdf"),
+            ControllerLayout::Controlify => include_str!("../assets/controller/controlify.vdf"),
+            ControllerLayout::MidnightControls => include_str!("../assets/controller/midnightcontrols.vdf"),
+        }
+    }
+
+    /// The template, made for `controller` instead of the Steam Deck.
+    fn template_for(&self, controller: &str) -> String {
+        let quoted = |name: &str| format!("\"controller_type\"\t\t\"{}\"", name);
+        self.template().replace(&quoted(TEMPLATE_CONTROLLER), &quoted(controller))
+    }
+
+    /// The mod jar names this layout is meant for, lowercase.
+    fn mod_names(&self) -> &'static [&'static str] {
+        match self {
+            ControllerLayout::KeyboardMouse => &[],
+            ControllerLayout::Controlify => &["controlify"],
+            ControllerLayout::MidnightControls => &["midnightcontrols"],
+        }
+    }
+}
+
+/// Writing Steam Input templates for exported shortcuts.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ControllerSettings {
+    pub enabled: bool,
+    /// Replace configurations that are already there, including ones edited in Steam.
+    pub overwrite: bool,
+}
+
+/// Pick the layout for an instance from the controller mods in its mods folder.
+/// Disabled mods (`.jar.disabled`) don't count.
+pub fn detect_layout(instance_path: &Path) -> ControllerLayout {
+    let Some(mods) = game_dir(instance_path).map(|dir| dir.join(MODS_DIR)) else { return ControllerLayout::KeyboardMouse };
+    let Ok(entries) = std::fs::read_dir(mods) else { return ControllerLayout::KeyboardMouse };
+    let jars: Vec<String> = entries.flatten()
+        .map(|e| e.file_name().to_string_lossy().to_lowercase())
+        .filter(|name| name.ends_with(".jar"))
+        .collect();
+
+    ControllerLayout::ALL.into_iter()
+        .find(|layout| layout.mod_names().iter().any(|m| jars.iter().any(|jar| jar.starts_with(m))))
+        .unwrap_or(ControllerLayout::KeyboardMouse)
+}
+
+/// `<Steam>/steamapps/common/Steam Controller Configs/<user id>/config`, found from
+/// `<Steam>/userdata/<user id>/config/shortcuts.vdf`.
+pub fn controller_config_dir(shortcuts_path: &Path) -> Option<PathBuf> {
+    let user_dir = shortcuts_path.parent()?.parent()?;
+    let steam_dir = steam_dir_from_shortcuts(shortcuts_path)?;
+    Some(steam_dir.join(CONTROLLER_CONFIGS_DIR).join(user_dir.file_name()?).join("config"))
+}
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// What Steam keys a non-Steam shortcut's controller configurations by, both its folder in the
+/// config dir and its entry in the config sets: the shortcut's name in lowercase, not its app_id.
+pub fn controller_config_key(app_name: &str) -> String {
+    app_name.to_lowercase()
+}
+
+/// Write each shortcut's layout as its configuration for every controller type, and select it
+/// in that controller's config set. `layouts` pairs shortcut names with layouts. A shortcut
+/// keeps a configuration Opal didn't write, or another one picked in Steam, unless `overwrite`
+/// is on. Returns how many configurations were written.
+pub fn install_controller_templates(files: &dyn FileIo, config_dir: &Path, layouts: &[(String, ControllerLayout)], overwrite: bool) -> io::Result<usize> {
+    let mut written = 0;
+    for controller in CONTROLLER_TYPES {
+        let set_path = config_dir.join(format!("configset_{}.vdf", controller));
+        let text = match files.read(&set_path) {
+            Ok(bytes) => Some(String::from_utf8(bytes).map_err(invalid)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let mut set = match &text {
+            Some(text) => Vdf::parse(text).map_err(invalid)?,
+            None => Vdf::new(Cow::Borrowed(CONFIG_SET_ROOT), Value::Obj(Obj::new())),
+        };
+        let apps = set.value.get_mut_obj().ok_or_else(|| invalid("The controller config set has no root section"))?;
+
+        let mut set_changed = false;
+        for (app_name, layout) in layouts {
+            let key = controller_config_key(app_name);
+            let selected = apps.get(key.as_str()).and_then(|values| values.first()?.get_obj());
+            let autosaved = selected.is_some_and(|entry| entry.contains_key(AUTOSAVE_KEY));
+            if selected.is_some() && !autosaved && !overwrite { continue; }
+
+            let template = layout.template_for(controller);
+            let path = config_dir.join(&key).join(format!("{}.vdf", controller));
+            // Whether the file needs writing, and whether it's Opal's afterwards.
+            let (write, ours) = match files.read(&path).map(String::from_utf8) {
+                Ok(Ok(existing)) if existing == template => (false, true),
+                Ok(Ok(existing)) => {
+                    // One of ours (e.g. from before a controller mod was added) can always be replaced.
+                    let replace = overwrite || ControllerLayout::ALL.iter().any(|l| existing == l.template_for(controller));
+                    (replace, replace)
+                }
+                // Not text, so not one of ours.
+                Ok(Err(_)) => (overwrite, overwrite),
+                Err(_) => (true, true),
+            };
+            if write {
+                files.create_dir_all(path.parent().unwrap_or(config_dir))?;
+                files.write(&path, template.as_bytes())?;
+                written += 1;
+            }
+            if ours && !autosaved {
+                let mut entry = Obj::new();
+                entry.insert(Cow::Borrowed(AUTOSAVE_KEY), vec![Value::Str(Cow::Borrowed("1"))]);
+                apps.insert(Cow::Owned(key), vec![Value::Obj(entry)]);
+                set_changed = true;
+            }
+        }
+        if set_changed {
+            files.create_dir_all(config_dir)?;
+            files.write(&set_path, set.to_string().as_bytes())?;
+        }
+    }
+    Ok(written)
+}
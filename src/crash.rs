@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::UNIX_EPOCH;
+use log::{info, warn};
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use super::launch::GameExit;
+use super::playtime::Session;
+use super::servers::game_dir;
+
+const CRASH_REPORTS_DIR : &str = "crash-reports";
+const LATEST_LOG_FILE : &str = "logs/latest.log";
+const SESSIONS_DIR : &str = "sessions";
+const CRASH_FILE : &str = "crash.json";
+/// The JVM writes `hs_err_pid<pid>.log` into its working folder when it crashes itself.
+const JVM_CRASH_PREFIX : &str = "hs_err_pid";
+/// Minecraft logs this when it shuts down normally.
+const CLEAN_EXIT_MARKER : &str = "Stopping!";
+/// Modids in Forge stack traces that are never the culprit.
+const NOT_SUSPECTS : [&str; 5] = ["minecraft", "forge", "neoforge", "fml", "mixin"];
+
+/// A Java exception at the start of a line, optionally as the cause of another.
+static EXCEPTION : LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(Caused by: )?[\w$.]+(Exception|Error)\b").unwrap());
+/// A stack frame that names the mod it's in, e.g. "at TRANSFORMER/create@0.5.1/...".
+static MOD_FRAME : LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bat (?:[A-Z-]+/)?([a-z][a-z0-9_]*)@[^/\s]+/").unwrap());
+
+/// A session that ended in a crash, archived in Opal's data folder with the game's log
+/// and crash report so it can be looked at after Big Picture is back.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Crash {
+    /// Instance folder name.
+    pub instance: String,
+    pub name: String,
+    /// When the session started, in milliseconds since the epoch.
+    pub started: u64,
+    /// The exception that ended the game, e.g. "java.lang.NullPointerException: ...".
+    pub exception: Option<String>,
+    /// Mods the crash report blames.
+    pub suspect_mods: Vec<String>,
+    /// How the game ended, e.g. "exit code 1", if that alone gave the crash away.
+    pub exit: Option<String>,
+    /// File names of what was copied into the archive.
+    pub files: Vec<String>,
+    /// Seen on the Logs page and put away.
+    pub dismissed: bool,
+    /// The archive folder this was loaded from.
+    #[serde(skip)]
+    pub archive: PathBuf,
+}
+impl Crash {
+    /// Hide the crash from the Logs page. The archive is kept.
+    pub fn dismiss(&mut self) -> io::Result<()> {
+        self.dismissed = true;
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(self.archive.join(CRASH_FILE), json)
+    }
+}
+
+fn sessions_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(SESSIONS_DIR)
+}
+
+/// Look for signs that the session crashed and, if it did, archive its log and crash report.
+/// A session crashed if the game exited abnormally (see `GameExit`, which isn't always known),
+/// if it left a new crash report (Minecraft's or the JVM's), or if its log ends in an exception
+/// without Minecraft's normal shutdown.
+pub fn capture_crash(data_dir: &Path, instance_path: &Path, session: &Session, exit: GameExit) -> io::Result<Option<Crash>> {
+    let Some(game_dir) = game_dir(instance_path) else { return Ok(None) };
+    let report = newest_file_since(&game_dir.join(CRASH_REPORTS_DIR), session.started, |_| true)
+        .or_else(|| newest_file_since(&game_dir, session.started, |name| name.starts_with(JVM_CRASH_PREFIX)));
+    let log_path = game_dir.join(LATEST_LOG_FILE);
+    let log = modified_ms(&log_path).filter(|t| *t >= session.started)
+        .and_then(|_| std::fs::read_to_string(&log_path).ok());
+    // Steam's "Exit game" also ends the game without a shutdown, so it also needs an error.
+    let unclean_exit = log.as_ref().is_some_and(|log| !log.contains(CLEAN_EXIT_MARKER) && find_exception(log).is_some());
+    if report.is_none() && !unclean_exit && !exit.is_abnormal() {
+        return Ok(None);
+    }
+
+    let report_text = report.as_ref().and_then(|path| std::fs::read_to_string(path).ok());
+    let (exception, suspect_mods) = match (&report_text, &log) {
+        (Some(text), _) => (find_exception(text), find_suspect_mods(text)),
+        (None, Some(log)) => (find_exception(log), find_suspect_mods(log)),
+        (None, None) => (None, Vec::new()),
+    };
+
+    let archive = sessions_dir(data_dir).join(format!("{}-{}", session.instance, session.started));
+    std::fs::create_dir_all(&archive)?;
+    let mut files = Vec::new();
+    for path in [Some(log_path).filter(|_| log.is_some()), report].into_iter().flatten() {
+        let Some(file_name) = path.file_name() else { continue };
+        std::fs::copy(&path, archive.join(file_name))?;
+        files.push(file_name.to_string_lossy().to_string());
+    }
+
+    let crash = Crash {
+        instance: session.instance.clone(),
+        name: session.name.clone(),
+        started: session.started,
+        exception,
+        suspect_mods,
+        exit: exit.is_abnormal().then(|| exit.to_string()),
+        files,
+        dismissed: false,
+        archive,
+    };
+    crash.save()?;
+    info!("Archived the crash of {} in {}", crash.instance, crash.archive.display());
+    Ok(Some(crash))
+}
+
+/// Crashes that haven't been dismissed yet, newest first.
+pub fn load_crashes(data_dir: &Path) -> Vec<Crash> {
+    let Ok(entries) = std::fs::read_dir(sessions_dir(data_dir)) else { return Vec::new() };
+    let mut crashes: Vec<Crash> = entries.flatten()
+        .filter_map(|entry| {
+            let json = std::fs::read_to_string(entry.path().join(CRASH_FILE)).ok()?;
+            match serde_json::from_str::<Crash>(&json) {
+                Ok(crash) => Some(Crash { archive: entry.path(), ..crash }),
+                Err(e) => {
+                    warn!("Skipping {}: {}", entry.path().display(), e);
+                    None
+                }
+            }
+        })
+        .filter(|crash| !crash.dismissed)
+        .collect();
+    crashes.sort_by_key(|crash| std::cmp::Reverse(crash.started));
+    crashes
+}
+
+fn modified_ms(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+/// The most recently changed file in `dir` whose name passes `filter`, if it changed after `since_ms`.
+fn newest_file_since(dir: &Path, since_ms: u64, filter: impl Fn(&str) -> bool) -> Option<PathBuf> {
+    std::fs::read_dir(dir).ok()?.flatten()
+        .filter(|e| e.path().is_file() && filter(&e.file_name().to_string_lossy()))
+        .filter_map(|e| Some((modified_ms(&e.path())?, e.path())))
+        .filter(|(modified, _)| *modified >= since_ms)
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// The exception in a crash report, which follows its description, or the last one in a log.
+/// For JVM crashes, the signal or error from the report's header.
+fn find_exception(text: &str) -> Option<String> {
+    if let Some(description) = text.find("\nDescription:") {
+        if let Some(line) = text[description..].lines().skip(1).map(str::trim).find(|l| EXCEPTION.is_match(l)) {
+            return Some(line.to_string());
+        }
+    }
+    if text.contains("A fatal error has been detected by the Java Runtime Environment") {
+        return text.lines()
+            .map(|l| l.trim_start_matches('#').trim())
+            .find(|l| l.starts_with("SIG") || l.starts_with("EXCEPTION_") || l.starts_with("Internal Error"))
+            .map(|l| format!("Java crashed: {}", l));
+    }
+    text.lines().rev()
+        .map(str::trim)
+        .find(|l| EXCEPTION.captures(l).is_some_and(|c| c.get(1).is_none()))
+        .map(str::to_string)
+}
+
+/// Forge and NeoForge list the suspected mods; otherwise, the mods in the stack trace.
+fn find_suspect_mods(text: &str) -> Vec<String> {
+    let mut mods: Vec<String> = Vec::new();
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some(rest) = line.trim().strip_prefix("Suspected Mod") else { continue };
+        let listed = rest.trim_start_matches('s').trim_start_matches(':').trim();
+        if !listed.is_empty() {
+            if listed != "NONE" { mods.push(listed.to_string()); }
+            continue;
+        }
+        // One per line below, with their details indented further.
+        while let Some(next) = lines.next_if(|l| l.starts_with('\t')) {
+            if !next.starts_with("\t\t") { mods.push(next.trim().to_string()); }
+        }
+    }
+    if mods.is_empty() {
+        for captures in MOD_FRAME.captures_iter(text) {
+            let modid = &captures[1];
+            if !NOT_SUSPECTS.contains(&modid) && !mods.iter().any(|m| m == modid) {
+                mods.push(modid.to_string());
+            }
+        }
+    }
+    // Reports repeat the list for every section that mentions the crash.
+    let mut seen = HashSet::new();
+    mods.retain(|m| seen.insert(m.clone()));
+    mods
+}
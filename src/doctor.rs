@@ -0,0 +1,239 @@
+use std::io;
+use std::path::Path;
+use log::error;
+use super::app::{AppModel, INSTANCES_DIR};
+use super::history::{ExportHistory, ExportRecord, PlannedShortcut};
+use super::instances::get_instances_from_path;
+use super::merge::{FieldPolicy, MergePolicy};
+use super::platform::FileIo;
+use super::steam::{edit_steam_shortcuts, read_steam_shortcuts, write_steam_shortcuts};
+use super::sync::{is_wrapped, launch_target, split_launch_options, LaunchTarget};
+use super::worlds::saves_dir;
+
+const WRITE_TEST_FILE : &str = ".opal_write_test";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// An automatic fix for a finding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fix {
+    /// Point the shortcut at the current PrismLauncher install (or Opal, for shortcuts that
+    /// go through the launch wrapper), keeping its app_id.
+    RepointShortcut { app_id: u32 },
+    RemoveShortcut { app_id: u32 },
+    /// Write the instance's shortcut again, with Opal's icon.
+    ReExport { folder_name: String },
+}
+impl Fix {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Fix::RepointShortcut { .. } => "Repoint shortcut",
+            Fix::RemoveShortcut { .. } => "Remove shortcut",
+            Fix::ReExport { .. } => "Re-export",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    /// What was checked, e.g. "PrismLauncher folder".
+    pub check: String,
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+impl Finding {
+    fn new(severity: Severity, check: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { severity, check: check.into(), message: message.into(), fix: None }
+    }
+
+    fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+/// Validate the whole chain from PrismLauncher to Steam's shortcuts.
+pub fn run_checks(model: &AppModel) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let config = &model.config;
+
+    // PrismLauncher side
+    if config.prism_main_path.is_dir() {
+        findings.push(Finding::new(Severity::Ok, "PrismLauncher folder", config.prism_main_path.display().to_string()));
+    } else {
+        findings.push(Finding::new(Severity::Error, "PrismLauncher folder",
+            format!("{} doesn't exist. Set it on the Settings page.", config.prism_main_path.display())));
+    }
+
+    let exe = model.prism_exe_path();
+    if Path::new(&exe).is_file() {
+        findings.push(Finding::new(Severity::Ok, "PrismLauncher executable", exe.clone()));
+    } else {
+        findings.push(Finding::new(Severity::Error, "PrismLauncher executable", format!("{} not found.", exe)));
+    }
+
+    match get_instances_from_path(model.instances_json_path(), true) {
+        Ok(instances) => findings.push(Finding::new(Severity::Ok, "Instance groups",
+            format!("instgroups.json lists {} instances.", instances.len()))),
+        Err(e) => findings.push(Finding::new(Severity::Error, "Instance groups",
+            format!("Couldn't read {}: {}", model.instances_json_path().display(), e))),
+    }
+
+    // Steam side
+    match model.platform.locator.steam_dir() {
+        Ok(steam) => findings.push(Finding::new(Severity::Ok, "Steam installation", steam.display().to_string())),
+        Err(e) => findings.push(Finding::new(Severity::Error, "Steam installation", format!("Steam wasn't found: {}", e))),
+    }
+
+    match config.steam_shortcuts_path.parent() {
+        Some(dir) => match check_writable(model.files(), dir) {
+            Ok(()) => findings.push(Finding::new(Severity::Ok, "Steam userdata", format!("{} is writable.", dir.display()))),
+            Err(e) => findings.push(Finding::new(Severity::Error, "Steam userdata", format!("Can't write to {}: {}", dir.display(), e))),
+        },
+        None => findings.push(Finding::new(Severity::Error, "Steam userdata", "The shortcuts path is empty.")),
+    }
+
+    let shortcuts = match read_steam_shortcuts(model.files(), &config.steam_shortcuts_path) {
+        Ok(shortcuts) => shortcuts,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            findings.push(Finding::new(Severity::Error, "Steam shortcuts",
+                format!("{} doesn't exist, so there's nothing to export to. Start Steam with this user once.", config.steam_shortcuts_path.display())));
+            return findings;
+        }
+        Err(e) => {
+            findings.push(Finding::new(Severity::Error, "Steam shortcuts", format!("Couldn't parse shortcuts.vdf: {}", e)));
+            return findings;
+        }
+    };
+
+    // Every shortcut Opal made
+    let instances_dir = config.prism_main_path.join(INSTANCES_DIR);
+    let mut opal_shortcuts = 0;
+    for shortcut in shortcuts.iter() {
+        let Some(LaunchTarget { instance: folder, server, world, account }) = launch_target(shortcut) else { continue };
+        opal_shortcuts += 1;
+        let check = format!("Shortcut \"{}\"", shortcut.app_name);
+        let app_id = shortcut.app_id;
+
+        if !instances_dir.join(&folder).is_dir() {
+            findings.push(Finding::new(Severity::Error, check,
+                format!("Instance folder {} no longer exists.", folder)).with_fix(Fix::RemoveShortcut { app_id }));
+            continue;
+        }
+        if !Path::new(shortcut.exe.trim_matches('"')).is_file() || !Path::new(shortcut.start_dir.trim_matches('"')).is_dir() {
+            findings.push(Finding::new(Severity::Error, check,
+                format!("Points to a missing {} ({}).", if is_wrapped(shortcut) { "Opal" } else { "PrismLauncher" }, shortcut.exe)).with_fix(Fix::RepointShortcut { app_id }));
+            continue;
+        }
+        if let Some(address) = server {
+            let attached = model.instance_options(&folder).servers.iter().any(|s| s.address.trim() == address);
+            if !attached {
+                findings.push(Finding::new(Severity::Warning, check,
+                    format!("Server {} is no longer attached to {}.", address, folder)).with_fix(Fix::RemoveShortcut { app_id }));
+                continue;
+            }
+        }
+        if let Some(world) = world {
+            if !model.instance_options(&folder).worlds.iter().any(|w| w.folder == world) {
+                findings.push(Finding::new(Severity::Warning, check,
+                    format!("World {} is no longer selected for {}.", world, folder)).with_fix(Fix::RemoveShortcut { app_id }));
+                continue;
+            }
+            if !saves_dir(&instances_dir.join(&folder)).is_some_and(|saves| saves.join(&world).is_dir()) {
+                findings.push(Finding::new(Severity::Warning, check,
+                    format!("World {} doesn't exist anymore.", world)).with_fix(Fix::RemoveShortcut { app_id }));
+                continue;
+            }
+        }
+        if let Some(account) = account {
+            let options = model.instance_options(&folder);
+            // Accounts typed into the extra launch options are the user's business.
+            let typed = split_launch_options(&options.extra_launch_options).contains(&account);
+            if !typed && !options.accounts.contains(&account) {
+                findings.push(Finding::new(Severity::Warning, check,
+                    format!("Account {} is no longer selected for {}.", account, folder)).with_fix(Fix::RemoveShortcut { app_id }));
+                continue;
+            }
+            if !model.accounts.contains(&account) {
+                findings.push(Finding::new(Severity::Warning, check.clone(),
+                    format!("There is no PrismLauncher account called {}.", account)));
+            }
+        }
+        if !shortcut.icon.is_empty() && !Path::new(shortcut.icon.trim_matches('"')).is_file() {
+            findings.push(Finding::new(Severity::Warning, check,
+                format!("Icon {} doesn't exist.", shortcut.icon)).with_fix(Fix::ReExport { folder_name: folder }));
+        }
+    }
+    findings.push(Finding::new(Severity::Ok, "Steam shortcuts",
+        format!("{} shortcuts, {} made by Opal.", shortcuts.len(), opal_shortcuts)));
+
+    findings
+}
+
+fn check_writable(files: &dyn FileIo, dir: &Path) -> io::Result<()> {
+    let test_file = dir.join(WRITE_TEST_FILE);
+    files.write(&test_file, b"")?;
+    files.remove(&test_file)
+}
+
+/// Apply fixes to `shortcuts.vdf`. The caller is responsible for closing Steam first.
+/// Re-exports are recorded like an export, so they can be undone.
+pub fn apply_fixes(model: &mut AppModel, fixes: &[Fix]) -> io::Result<()> {
+    let path = model.config.steam_shortcuts_path.clone();
+    let exe = model.prism_exe_path();
+    let opal_exe = std::env::current_exe()?.to_string_lossy().to_string();
+    let start_dir = model.config.prism_main_path.to_string_lossy().to_string();
+
+    let re_exports: Vec<_> = fixes.iter()
+        .filter_map(|fix| match fix {
+            Fix::ReExport { folder_name } => model.instances.iter().find(|i| &i.folder_name == folder_name),
+            _ => None,
+        })
+        .flat_map(|inst| model.shortcuts_for(inst))
+        .collect();
+    // Begun before any fix is applied, so undoing puts back all of them.
+    let record = match re_exports.is_empty() {
+        true => None,
+        false => Some(ExportRecord::begin(model.files(), &path, &re_exports)?),
+    };
+
+    edit_steam_shortcuts(model.files(), &path, |shortcuts| {
+        for fix in fixes {
+            match fix {
+                Fix::RepointShortcut { app_id } => {
+                    for shortcut in shortcuts.iter_mut().filter(|s| s.app_id == *app_id) {
+                        shortcut.exe = if is_wrapped(shortcut) { opal_exe.clone() } else { exe.clone() };
+                        shortcut.start_dir = start_dir.clone();
+                    }
+                }
+                Fix::RemoveShortcut { app_id } => shortcuts.retain(|s| s.app_id != *app_id),
+                Fix::ReExport { .. } => {}
+            }
+        }
+    })?;
+
+    let Some(mut record) = record else { return Ok(()) };
+    // The icon in Steam is the broken part, so don't keep it.
+    let policy = MergePolicy { icon: FieldPolicy::Opal, ..model.config.merge_policy.clone() };
+    let app_ids = write_steam_shortcuts(model.files(), &path, re_exports, &policy, model.config.shortcut_placement, model.last_plan())?;
+    for (planned, app_id) in record.plan.iter_mut().zip(app_ids) {
+        planned.app_id = app_id;
+    }
+    // The other shortcuts are still the way the last export planned them.
+    let untouched: Vec<PlannedShortcut> = model.last_plan().iter()
+        .filter(|p| !record.plan.iter().any(|r| r.app_id == p.app_id))
+        .cloned()
+        .collect();
+    record.plan.extend(untouched);
+    if let Err(e) = model.data_dir().and_then(|dir| ExportHistory::record(dir, record)) {
+        error!("Couldn't save the export history, this re-export can't be undone! {}", e);
+    }
+    model.update_export_history();
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use eframe::egui::*;
+use log::error;
+use super::ui::TabPage;
+use super::app::{AppModel, APP_HEADER_PADDING};
+use super::doctor::{apply_fixes, run_checks, Finding, Fix, Severity};
+
+/// The “Diagnostics” tab: runs the doctor checks and offers their fixes.
+#[derive(Default)]
+pub struct DoctorPage {
+    findings: Option<Vec<Finding>>,
+}
+
+impl DoctorPage {
+    fn fix(&mut self, model: &mut AppModel, fixes: Vec<Fix>) {
+        let result = model.while_steam_closed(|model| apply_fixes(model, &fixes));
+        if let Err(e) = result {
+            error!("Couldn't apply fixes! {e}");
+        }
+        self.findings = Some(run_checks(model));
+    }
+}
+
+impl TabPage for DoctorPage {
+    fn id(&self) -> &'static str { "doctor" }
+    fn label(&self) -> &'static str { "Diagnostics" }
+
+    fn ui(&mut self, ui: &mut Ui, model: &mut AppModel) {
+        ui.heading("Diagnostics");
+
+        ui.add_space(APP_HEADER_PADDING);
+
+        let mut fixes = Vec::new();
+        ui.horizontal(|ui| {
+            if ui.button("Run Checks").clicked() {
+                self.findings = Some(run_checks(model));
+            }
+            let all_fixes: Vec<Fix> = self.findings.iter().flatten().filter_map(|f| f.fix.clone()).collect();
+            if ui.add_enabled(!all_fixes.is_empty(), Button::new("Fix All")).clicked() {
+                fixes = all_fixes;
+            }
+        });
+
+        ui.separator();
+
+        let Some(findings) = &self.findings else {
+            ui.label("Checks haven't been run yet.");
+            return;
+        };
+
+        ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+            Grid::new("doctor_findings").num_columns(4).spacing([12.0, 6.0]).striped(true).show(ui, |ui| {
+                for finding in findings {
+                    match finding.severity {
+                        Severity::Ok => ui.colored_label(Color32::from_rgb(90, 170, 90), "✔"),
+                        Severity::Warning => ui.colored_label(ui.visuals().warn_fg_color, "⚠"),
+                        Severity::Error => ui.colored_label(ui.visuals().error_fg_color, "✖"),
+                    };
+                    ui.strong(&finding.check);
+                    ui.label(&finding.message);
+                    match &finding.fix {
+                        Some(fix) => if ui.button(fix.label()).clicked() { fixes.push(fix.clone()); },
+                        None => { ui.label(""); }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+        if !fixes.is_empty() {
+            self.fix(model, fixes);
+        }
+    }
+}
@@ -1,16 +1,128 @@
-use super::ui::TabPage;
-use eframe::egui::*;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
 use std::time::Duration;
+use log::{error, info, warn};
+use super::ui::{TabPage, format_play_time, format_time_ago};
+use eframe::egui::*;
+use egui_extras::{Column, TableBuilder};
 use super::app::{AppModel, APP_HEADER_PADDING};
-use super::steam::{start_steam, ensure_steam_started, ensure_steam_stopped};
+use super::backup::{back_up_worlds, list_backups, restore_world, Backup, BackupSettings};
+use super::compat::CompatTool;
+use super::controller::{detect_layout, ControllerLayout};
+use super::launch::{is_game_running, AlreadyRunning};
+use super::settings_page::{backup_keep_ui, compat_tool_menu, hooks_ui};
+use super::instance_options::InstanceOptions;
+use super::instances::{Instance, ModLoader};
+use super::servers::{read_server_list, Server};
+use super::worlds::{scan_worlds, World, WorldShortcut};
+use super::sync::SyncStatus;
+use super::rules::{compare_versions, now_ms, CompiledRules, RuleAction, RuleCondition, RuleSet, SelectionRule};
+
+const APP_RULE_TEXT_WIDTH : f32 = 160.0;
+const APP_TABLE_ROW_HEIGHT : f32 = 22.0;
+const APP_EXPORT_BUTTON_SPACE : f32 = 40.0;
+const APP_DETAIL_PANEL_WIDTH : f32 = 300.0;
+const UNGROUPED_LABEL : &str = "Ungrouped";
+const IMAGE_EXTENSIONS : [&str; 4] = ["png", "jpg", "jpeg", "ico"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortColumn {
+    Name,
+    Group,
+    McVersion,
+    Loader,
+    LastPlayed,
+    PlayTime,
+    Status,
+}
+impl SortColumn {
+    const ALL : [SortColumn; 7] = [
+        SortColumn::Name, SortColumn::Group, SortColumn::McVersion, SortColumn::Loader,
+        SortColumn::LastPlayed, SortColumn::PlayTime, SortColumn::Status,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SortColumn::Name => "Name",
+            SortColumn::Group => "Group",
+            SortColumn::McVersion => "Minecraft",
+            SortColumn::Loader => "Loader",
+            SortColumn::LastPlayed => "Last Played",
+            SortColumn::PlayTime => "Play Time",
+            SortColumn::Status => "Status",
+        }
+    }
+
+    fn compare(&self, a: &Instance, b: &Instance) -> Ordering {
+        match self {
+            SortColumn::Name => a.display_name().to_lowercase().cmp(&b.display_name().to_lowercase()),
+            SortColumn::Group => a.group.to_lowercase().cmp(&b.group.to_lowercase()),
+            SortColumn::McVersion => match (&a.mc_version, &b.mc_version) {
+                (Some(a), Some(b)) => compare_versions(a, b),
+                (a, b) => a.is_some().cmp(&b.is_some()),
+            },
+            SortColumn::Loader => a.loader.name().cmp(b.loader.name()),
+            SortColumn::LastPlayed => a.last_launch.cmp(&b.last_launch),
+            SortColumn::PlayTime => a.total_time_played.cmp(&b.total_time_played),
+            SortColumn::Status => a.status.rank().cmp(&b.status.rank()),
+        }
+    }
+}
+
+/// One line of the instance table.
+enum TableRow {
+    /// Group name and the (visible) instances in it.
+    Group(String, Vec<usize>),
+    Instance(usize),
+}
 
-const APP_INSTANCE_GRID_COLS : usize = 3;
-const APP_INSTANCE_GRID_MAX_HEIGHT : f32 = 200.0;
+enum SelectAction {
+    All,
+    None,
+    Invert,
+}
 
+/// The “Export” tab. Keeps the table's search, sort and collapsed groups between frames.
+pub struct ExportPage {
+    search: String,
+    sort: SortColumn,
+    ascending: bool,
+    group_rows: bool,
+    collapsed: HashSet<String>,
+    /// Folder name of the instance shown in the detail panel.
+    selected: Option<String>,
+    /// The worlds of the selected instance, with the folder they were scanned for.
+    worlds: Option<(String, Vec<World>)>,
+    /// The world backups of the selected instance, likewise.
+    backups: Option<(String, Vec<Backup>)>,
+    /// The controller layout detected for the selected instance, likewise.
+    detected_layout: Option<(String, ControllerLayout)>,
+    /// The selection rules as last compiled, rebuilt when they're edited.
+    compiled_rules: Option<CompiledRules>,
+    /// "Back Up Now" running on another thread, with the instance folder it backs up.
+    backup_job: Option<(String, JoinHandle<io::Result<Option<PathBuf>>>)>,
+}
 
-/// The “Export” tab (no special state for this simple example).
-#[derive(Default)]
-pub struct ExportPage;
+impl Default for ExportPage {
+    fn default() -> Self {
+        Self {
+            search: String::new(),
+            sort: SortColumn::Name,
+            ascending: true,
+            group_rows: true,
+            collapsed: HashSet::new(),
+            selected: None,
+            worlds: None,
+            backups: None,
+            detected_layout: None,
+            compiled_rules: None,
+            backup_job: None,
+        }
+    }
+}
 
 impl TabPage for ExportPage {
     fn id(&self) -> &'static str { "export" }
@@ -21,51 +133,727 @@ impl TabPage for ExportPage {
 
         ui.add_space(APP_HEADER_PADDING);
 
+        CollapsingHeader::new("Selection Rules")
+            .id_salt("selection_rules")
+            .show(ui, |ui| rules_ui(ui, model, &mut self.compiled_rules));
+
+        ui.separator();
+
+        if self.selected.is_some() {
+            SidePanel::right("instance_details")
+                .resizable(true)
+                .default_width(APP_DETAIL_PANEL_WIDTH)
+                .show_inside(ui, |ui| self.details_ui(ui, model));
+        }
+
         ui.heading("Instances Found:");
 
-        ScrollArea::vertical()
-                .id_salt("instances_scroll_grid")
-                .max_height(APP_INSTANCE_GRID_MAX_HEIGHT) // or: .max_height(ui.available_height())
-                .auto_shrink([false; 2])
-                .show(ui, |ui| {
-                    if model.instances.is_empty() {
-                        ui.label(format!("No PrismLauncher instance found in specified path."));
-                        return;
-                    } else {
-                        Grid::new("instances_grid")
-                            .num_columns(APP_INSTANCE_GRID_COLS)
-                            .spacing([12.0, 8.0])
-                            .striped(false)
-                            .show(ui, |ui| {
-                                for row in model.instances.chunks_mut(APP_INSTANCE_GRID_COLS) {
-                                    for inst in row {
-                                        ui.checkbox(&mut inst.checked, &inst.folder_name);
-                                    }
-                                    // optional: pad short last rows
-                                    // for _ in row.len()..APP_INSTANCE_GRID_COLS { ui.allocate_space(egui::vec2(0.0, 0.0)); }
-                                    ui.end_row();
+        if model.instances.is_empty() {
+            ui.label("No PrismLauncher instance found in specified path.");
+        } else {
+            self.instance_table(ui, model);
+        }
+
+        orphans_ui(ui, model);
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.button("Export Selected to Steam Shortcuts").clicked() {
+                // Errors are already logged.
+                let _ = model.export_to_steam();
+                model.save_config();
+            }
+            let last = model.export_history.last().map(|r| r.time);
+            let undo = ui.add_enabled(last.is_some(), Button::new("Undo Last Export"))
+                .on_hover_text("Put shortcuts.vdf back the way it was before the last export and delete the artwork it added. Changes made in Steam since then are lost.")
+                .on_disabled_hover_text("Nothing has been exported yet.");
+            if undo.clicked() {
+                if let Ok(record) = model.undo_last_export() {
+                    info!("Undid the export from {}", format_time_ago(Some(record.time), now_ms()));
+                }
+            }
+        });
+
+    }
+}
+
+impl ExportPage {
+    fn matches_search(&self, inst: &Instance) -> bool {
+        let needle = self.search.trim().to_lowercase();
+        needle.is_empty()
+            || inst.display_name().to_lowercase().contains(&needle)
+            || inst.folder_name.to_lowercase().contains(&needle)
+            || inst.group.to_lowercase().contains(&needle)
+            || inst.mc_version.as_deref().is_some_and(|v| v.contains(&needle))
+            || inst.loader.name().to_lowercase().contains(&needle)
+    }
+
+    /// Filter, sort and (optionally) group the instances into table rows.
+    /// `model.instances` is already in a stable default order and `sort_by` is stable,
+    /// so ties keep that order.
+    fn visible_rows(&self, model: &AppModel) -> Vec<TableRow> {
+        let mut indices: Vec<usize> = (0..model.instances.len())
+            .filter(|&i| self.matches_search(&model.instances[i]))
+            .collect();
+        indices.sort_by(|&a, &b| {
+            let ord = self.sort.compare(&model.instances[a], &model.instances[b]);
+            if self.ascending { ord } else { ord.reverse() }
+        });
+
+        if !self.group_rows {
+            return indices.into_iter().map(TableRow::Instance).collect();
+        }
+
+        // Groups appear in the order of their first member: the default order
+        // (ungrouped last), or the sort order when sorting by group.
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+        for i in indices {
+            let group = &model.instances[i].group;
+            match groups.iter_mut().find(|(name, _)| name == group) {
+                Some((_, members)) => members.push(i),
+                None => groups.push((group.clone(), vec![i])),
+            }
+        }
+        if self.sort != SortColumn::Group {
+            groups.sort_by_key(|(name, _)| (name.is_empty(), name.to_lowercase()));
+        }
+
+        let mut rows = Vec::new();
+        for (name, members) in groups {
+            let collapsed = self.collapsed.contains(&name);
+            rows.push(TableRow::Group(name, members.clone()));
+            if !collapsed {
+                rows.extend(members.into_iter().map(TableRow::Instance));
+            }
+        }
+        rows
+    }
+
+    fn instance_table(&mut self, ui: &mut Ui, model: &mut AppModel) {
+        let rows = self.visible_rows(model);
+        let mut actions: Vec<(Vec<usize>, SelectAction)> = Vec::new();
+
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut self.search).hint_text("🔍 Search").desired_width(APP_RULE_TEXT_WIDTH));
+            ui.checkbox(&mut self.group_rows, "Group by Prism group");
+            ui.separator();
+            let visible: Vec<usize> = (0..model.instances.len())
+                .filter(|&i| self.matches_search(&model.instances[i]))
+                .collect();
+            if let Some(action) = select_buttons(ui) { actions.push((visible, action)); }
+        });
+
+        let now = now_ms();
+        let table_height = ui.available_height() - APP_EXPORT_BUTTON_SPACE;
+        let mut toggle_group = None;
+        let mut sort_clicked = None;
+        let mut select = None;
+
+        TableBuilder::new(ui)
+            .id_salt("instances_table")
+            .striped(true)
+            .resizable(true)
+            .cell_layout(Layout::left_to_right(Align::Center))
+            .max_scroll_height(table_height)
+            .column(Column::exact(24.0))
+            .column(Column::initial(220.0).at_least(80.0).clip(true))
+            .column(Column::initial(140.0).at_least(60.0).clip(true))
+            .columns(Column::initial(90.0).at_least(50.0), 4)
+            .column(Column::remainder().at_least(80.0))
+            .header(APP_TABLE_ROW_HEIGHT, |mut header| {
+                header.col(|_| {});
+                for column in SortColumn::ALL {
+                    header.col(|ui| {
+                        let arrow = match (self.sort == column, self.ascending) {
+                            (false, _) => "",
+                            (true, true) => " ⏶",
+                            (true, false) => " ⏷",
+                        };
+                        if ui.selectable_label(self.sort == column, format!("{}{}", column.label(), arrow)).clicked() {
+                            sort_clicked = Some(column);
+                        }
+                    });
+                }
+            })
+            .body(|mut body| {
+                for row in rows {
+                    match row {
+                        TableRow::Group(name, members) => body.row(APP_TABLE_ROW_HEIGHT, |mut row| {
+                            let collapsed = self.collapsed.contains(&name);
+                            row.col(|ui| {
+                                if ui.small_button(if collapsed { "⏵" } else { "⏷" }).clicked() {
+                                    toggle_group = Some(name.clone());
                                 }
                             });
+                            row.col(|ui| {
+                                let title = if name.is_empty() { UNGROUPED_LABEL } else { &name };
+                                ui.strong(format!("{} ({})", title, members.len()));
+                            });
+                            row.col(|ui| {
+                                if let Some(action) = select_buttons(ui) { actions.push((members.clone(), action)); }
+                            });
+                            for _ in 3..8 { row.col(|_| {}); }
+                        }),
+                        TableRow::Instance(i) => body.row(APP_TABLE_ROW_HEIGHT, |mut row| {
+                            let inst = &mut model.instances[i];
+                            row.col(|ui| { ui.checkbox(&mut inst.checked, ""); });
+                            row.col(|ui| {
+                                let selected = self.selected.as_ref() == Some(&inst.folder_name);
+                                if ui.selectable_label(selected, inst.display_name()).on_hover_text(&inst.folder_name).clicked() {
+                                    select = Some(inst.folder_name.clone());
+                                }
+                            });
+                            row.col(|ui| { ui.label(&inst.group); });
+                            row.col(|ui| { ui.label(inst.mc_version.as_deref().unwrap_or("?")); });
+                            row.col(|ui| { ui.label(inst.loader.name()); });
+                            row.col(|ui| { ui.label(format_time_ago(inst.last_launch, now)); });
+                            row.col(|ui| { ui.label(format_play_time(inst.total_time_played)); });
+                            row.col(|ui| { status_badge(ui, &inst.status); });
+                        }),
                     }
-                });
-        
+                }
+            });
+
+        if let Some(folder) = select {
+            self.selected = if self.selected.as_ref() == Some(&folder) { None } else { Some(folder) };
+        }
+        if let Some(name) = toggle_group {
+            if !self.collapsed.remove(&name) { self.collapsed.insert(name); }
+        }
+        if let Some(column) = sort_clicked {
+            if self.sort == column { self.ascending = !self.ascending; }
+            else { self.sort = column; self.ascending = true; }
+        }
+        for (members, action) in actions {
+            for i in members {
+                let inst = &mut model.instances[i];
+                inst.checked = match action {
+                    SelectAction::All => true,
+                    SelectAction::None => false,
+                    SelectAction::Invert => !inst.checked,
+                };
+            }
+        }
+    }
+}
+
+impl ExportPage {
+    /// Per-instance shortcut options. Only options that differ from the defaults are saved.
+    fn details_ui(&mut self, ui: &mut Ui, model: &mut AppModel) {
+        let Some(folder) = self.selected.clone() else { return };
+        let Some(inst) = model.instances.iter().find(|i| i.folder_name == folder) else {
+            self.selected = None;
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.heading(inst.display_name());
+            if ui.small_button("✖").clicked() { self.selected = None; }
+        });
+        ui.weak(&inst.folder_name);
+        status_badge(ui, &inst.status);
         ui.separator();
+        let inst_path = inst.path.clone();
+        let inst_group = inst.group.clone();
+        let detected_layout = match &self.detected_layout {
+            Some((detected_for, layout)) if *detected_for == folder => *layout,
+            _ => {
+                let layout = detect_layout(&inst_path);
+                self.detected_layout = Some((folder.clone(), layout));
+                layout
+            }
+        };
+        if self.worlds.as_ref().is_none_or(|(scanned, _)| *scanned != folder) {
+            self.scan_worlds(&folder, &inst_path);
+        }
+        if self.backups.as_ref().is_none_or(|(listed, _)| *listed != folder) {
+            self.backups = Some((folder.clone(), backups_of(model, &folder)));
+        }
 
-        if ui.button("Export Selected to Steam Shortcuts").clicked() {
-              if let Err(e) = ensure_steam_stopped(Duration::from_millis(1000)) {
-                model.log_printout.push_str(&format!("\nFailed to close Steam: {e}"));
-              }
+        let mut options = model.instance_options(&folder);
+        let before = options.clone();
 
-              model.update_steam_shortcuts();
-              
-              if let Err(e) = start_steam() {
-                model.log_printout.push_str(&format!("\nFailed to start Steam: {e}"));
-              }
-              if let Err(e) = ensure_steam_started(Duration::from_millis(1000)) {
-                model.log_printout.push_str(&format!("\nFailed to check if Steam started: {e}"));
-              }
+        ScrollArea::vertical().id_salt("instance_details_scroll").show(ui, |ui| {
+            options_ui(ui, &mut options);
+            ui.add_space(8.0);
+            controller_layout_ui(ui, &mut options.controller_layout, detected_layout);
+            if cfg!(target_os = "linux") {
+                compat_tool_ui(ui, &mut options.compat_tool, &model.config.compat_tool, &model.compat_tools);
+            }
+            ui.add_space(8.0);
+            accounts_ui(ui, &mut options.accounts, &model.accounts);
+            ui.add_space(8.0);
+            servers_ui(ui, &mut options.servers, &inst_path);
+            ui.add_space(8.0);
+            let found = self.worlds.as_ref().map(|(_, worlds)| worlds.as_slice()).unwrap_or_default();
+            if worlds_ui(ui, &mut options.worlds, found) {
+                self.scan_worlds(&folder, &inst_path);
+            }
+            ui.add_space(8.0);
+            self.backups_ui(ui, &mut options.backup, &folder, &inst_path, model);
+            ui.add_space(8.0);
+            already_running_ui(ui, &mut options.already_running, model.config.launch.already_running);
+            ui.add_space(8.0);
+            ui.strong("Launch Hooks");
+            if options.hooks.is_empty() && model.config.launch.group_hooks.contains_key(&inst_group) {
+                ui.weak(format!("Uses the hooks of the {} group.", inst_group));
+            }
+            hooks_ui(ui, "instance", &mut options.hooks);
+            if !model.config.launch.use_wrapper {
+                ui.weak("Hooks only run for shortcuts that launch through Opal (see Settings).");
+            }
+            ui.add_space(8.0);
+            if ui.button("Reset to Defaults").clicked() {
+                options = InstanceOptions::default();
+            }
+        });
 
+        if options != before {
+            if options == InstanceOptions::default() {
+                model.config.instance_options.remove(&folder);
+            } else {
+                model.config.instance_options.insert(folder, options);
             }
+        }
+    }
+}
+
+impl ExportPage {
+    fn scan_worlds(&mut self, folder: &str, inst_path: &Path) {
+        let worlds = scan_worlds(inst_path).unwrap_or_else(|e| {
+            error!("Couldn't read the worlds of {}! {}", folder, e);
+            Vec::new()
+        });
+        self.worlds = Some((folder.to_string(), worlds));
+    }
+
+    /// The instance's backup settings, and its backups with a restore button per world.
+    fn backups_ui(&mut self, ui: &mut Ui, settings: &mut Option<BackupSettings>, folder: &str, inst_path: &Path, model: &mut AppModel) {
+        ui.horizontal(|ui| {
+            ui.strong("World Backups");
+            if ui.small_button("⟳").on_hover_text("List the backups again").clicked() {
+                self.backups = Some((folder.to_string(), backups_of(model, folder)));
+            }
+        });
+        let default = &model.config.launch.backup;
+        let default_label = match default.enabled {
+            true => format!("Use the default (keep {})", default.keep),
+            false => String::from("Use the default (off)"),
+        };
+        let mut use_default = settings.is_none();
+        if ui.checkbox(&mut use_default, default_label).changed() {
+            *settings = if use_default { None } else { Some(default.clone()) };
+        }
+        if let Some(settings) = settings {
+            ui.checkbox(&mut settings.enabled, "Back up worlds before each launch");
+            ui.add_enabled_ui(settings.enabled, |ui| backup_keep_ui(ui, settings));
+        }
+        if !model.config.launch.use_wrapper {
+            ui.weak("Backups are only made for shortcuts that launch through Opal (see Settings).");
+        }
+
+        let mut relist = false;
+        // Zipping large worlds takes a while, so it runs next to the UI.
+        if let Some((backing_up, job)) = self.backup_job.take_if(|(_, job)| job.is_finished()) {
+            match job.join() {
+                Ok(Ok(Some(_))) => relist = backing_up == folder,
+                Ok(Ok(None)) => info!("{} has no worlds to back up.", backing_up),
+                Ok(Err(e)) => error!("Couldn't back up the worlds of {}! {}", backing_up, e),
+                Err(_) => error!("The backup of {} crashed!", backing_up),
+            }
+        }
+        if let Some((backing_up, _)) = &self.backup_job {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Backing up the worlds of {}...", backing_up));
+            });
+            ui.ctx().request_repaint_after(Duration::from_millis(200));
+        } else if ui.button("Back Up Now").clicked() {
+            let keep = settings.as_ref().unwrap_or(&model.config.launch.backup).keep;
+            match model.data_dir() {
+                Ok(dir) => {
+                    let (dir, inst_path, name) = (dir.to_path_buf(), inst_path.to_path_buf(), folder.to_string());
+                    let job = std::thread::spawn(move || back_up_worlds(&dir, &inst_path, &name, keep));
+                    self.backup_job = Some((folder.to_string(), job));
+                }
+                Err(e) => error!("Couldn't back up the worlds of {}! {}", folder, e),
+            }
+        }
+
+        let backups = self.backups.as_ref().map(|(_, backups)| backups.as_slice()).unwrap_or_default();
+        if backups.is_empty() { ui.weak("No backups yet."); }
+        let now = now_ms();
+        let mut restore = None;
+        for backup in backups {
+            let title = format!("{} · {:.1} MB", format_time_ago(Some(backup.created), now), backup.size as f64 / 1_000_000.0);
+            CollapsingHeader::new(title).id_salt(&backup.path).show(ui, |ui| {
+                ui.weak(backup.path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default());
+                for world in backup.worlds.iter() {
+                    ui.horizontal(|ui| {
+                        ui.label(world);
+                        if ui.small_button("Restore").on_hover_text("The current copy of the world is kept, renamed").clicked() {
+                            restore = Some((backup.path.clone(), world.clone()));
+                        }
+                    });
+                }
+            });
+        }
+
+        if let Some((path, world)) = restore {
+            if is_game_running(inst_path) {
+                warn!("Close {} before restoring a world.", folder);
+            } else if let Err(e) = restore_world(&path, &world, inst_path) {
+                error!("Couldn't restore {}! {}", world, e);
+            } else {
+                self.scan_worlds(folder, inst_path);
+            }
+        }
+        if relist {
+            self.backups = Some((folder.to_string(), backups_of(model, folder)));
+        }
+    }
+}
+
+/// The backups of an instance, none if there's no data folder to keep them in.
+fn backups_of(model: &AppModel, folder: &str) -> Vec<Backup> {
+    model.data_dir().map(|dir| list_backups(dir, folder)).unwrap_or_default()
+}
+
+fn options_ui(ui: &mut Ui, options: &mut InstanceOptions) {
+    ui.strong("Steam Shortcut");
+    ui.checkbox(&mut options.hidden, "Hidden in library");
+    ui.checkbox(&mut options.allow_overlay, "Allow Steam overlay");
+    ui.checkbox(&mut options.allow_desktop_config, "Use desktop controller configuration");
+    ui.checkbox(&mut options.open_vr, "Launch in VR");
+
+    ui.label("Extra launch options:");
+    ui.add(TextEdit::singleline(&mut options.extra_launch_options).hint_text("Passed to PrismLauncher"));
+
+    ui.add_space(8.0);
+    ui.strong("Tags");
+    let mut remove = None;
+    for (i, tag) in options.extra_tags.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(tag);
+            if ui.small_button("🗑").clicked() { remove = Some(i); }
+        });
+    }
+    if let Some(i) = remove { options.extra_tags.remove(i); }
+    if ui.button("Add Tag").clicked() { options.extra_tags.push(String::new()); }
+
+    ui.add_space(8.0);
+    ui.strong("Icon and Artwork");
+    image_picker(ui, "Icon", &mut options.icon);
+    image_picker(ui, "Portrait", &mut options.artwork.grid);
+    image_picker(ui, "Wide", &mut options.artwork.wide);
+    image_picker(ui, "Hero", &mut options.artwork.hero);
+    image_picker(ui, "Logo", &mut options.artwork.logo);
+}
+
+fn controller_layout_ui(ui: &mut Ui, layout: &mut Option<ControllerLayout>, detected: ControllerLayout) {
+    ui.horizontal(|ui| {
+        let name_label = ui.label("Controller layout:");
+        let auto = format!("Automatic ({})", detected.label());
+        ComboBox::from_id_salt("controller_layout")
+            .selected_text(layout.map(|l| l.label().to_string()).unwrap_or_else(|| auto.clone()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(layout, None, auto);
+                for option in ControllerLayout::ALL {
+                    ui.selectable_value(layout, Some(option), option.label());
+                }
+            })
+            .response
+            .labelled_by(name_label.id);
+    });
+}
+
+fn already_running_ui(ui: &mut Ui, value: &mut Option<AlreadyRunning>, default: AlreadyRunning) {
+    ui.horizontal(|ui| {
+        let name_label = ui.label("When already running:");
+        let default_label = format!("Default ({})", default.label());
+        ComboBox::from_id_salt("already_running")
+            .selected_text(value.map(|v| v.label().to_string()).unwrap_or_else(|| default_label.clone()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(value, None, default_label);
+                for option in AlreadyRunning::ALL {
+                    ui.selectable_value(value, Some(option), option.label());
+                }
+            })
+            .response
+            .labelled_by(name_label.id);
+    });
+}
+
+fn compat_tool_ui(ui: &mut Ui, tool: &mut Option<CompatTool>, default: &CompatTool, tools: &[String]) {
+    ui.horizontal(|ui| {
+        let name_label = ui.label("Steam Play:");
+        let default_label = format!("Default ({})", default.label());
+        ComboBox::from_id_salt("compat_tool")
+            .selected_text(tool.as_ref().map(|t| t.label()).unwrap_or_else(|| default_label.clone()))
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(tool.is_none(), default_label).clicked() { *tool = None; }
+                let mut value = tool.clone().unwrap_or_else(|| default.clone());
+                let before = value.clone();
+                compat_tool_menu(ui, &mut value, tools);
+                if value != before { *tool = Some(value); }
+            })
+            .response
+            .labelled_by(name_label.id);
+    });
+}
+
+/// Which PrismLauncher accounts the shortcuts launch with.
+fn accounts_ui(ui: &mut Ui, selected: &mut Vec<String>, accounts: &[String]) {
+    ui.strong("Accounts");
+    if accounts.is_empty() {
+        ui.weak("No accounts found in PrismLauncher.");
+    } else {
+        ui.weak("With more than one, every shortcut is exported once per account.");
+    }
+    for account in accounts {
+        let mut checked = selected.contains(account);
+        if ui.checkbox(&mut checked, account).changed() {
+            match checked {
+                true => selected.push(account.clone()),
+                false => selected.retain(|a| a != account),
+            }
+        }
+    }
+
+    // Accounts that were removed from Prism since they were picked.
+    let mut remove = None;
+    for (i, account) in selected.iter().enumerate().filter(|(_, a)| !accounts.contains(a)) {
+        ui.horizontal(|ui| {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {} (not in PrismLauncher)", account));
+            if ui.small_button("🗑").clicked() { remove = Some(i); }
+        });
+    }
+    if let Some(i) = remove { selected.remove(i); }
+}
+
+/// Quick-join servers, each exported as its own shortcut.
+fn servers_ui(ui: &mut Ui, servers: &mut Vec<Server>, inst_path: &Path) {
+    ui.strong("Servers");
+    ui.weak("Each server gets its own shortcut that joins it directly.");
+    let mut remove = None;
+    for (i, server) in servers.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(TextEdit::singleline(&mut server.name).hint_text("Name").desired_width(100.0));
+                ui.add(TextEdit::singleline(&mut server.address).hint_text("host:port").desired_width(120.0));
+                if ui.small_button("🗑").clicked() { remove = Some(i); }
+            });
+            image_picker(ui, "Icon", &mut server.icon);
+        });
+    }
+    if let Some(i) = remove { servers.remove(i); }
+
+    ui.horizontal(|ui| {
+        if ui.button("Add Server").clicked() { servers.push(Server::default()); }
+        if ui.button("Import Server List").on_hover_text("Add the servers from the instance's multiplayer list").clicked() {
+            match read_server_list(inst_path) {
+                Ok(found) => {
+                    for server in found {
+                        if !servers.iter().any(|s| s.address == server.address) { servers.push(server); }
+                    }
+                }
+                Err(e) => {
+                    error!("Couldn't read the server list! {}", e);
+                }
+            }
+        }
+    });
+}
+
+/// Worlds to make direct-launch shortcuts for. Returns whether a rescan was requested.
+fn worlds_ui(ui: &mut Ui, selected: &mut Vec<WorldShortcut>, found: &[World]) -> bool {
+    let rescan = ui.horizontal(|ui| {
+        ui.strong("Worlds");
+        ui.small_button("⟳").on_hover_text("Scan the saves folder again").clicked()
+    }).inner;
+
+    ui.weak("Checked worlds get their own shortcut that loads straight into them.");
+    if found.is_empty() { ui.weak("No worlds yet."); }
+    let now = now_ms();
+    for world in found {
+        let position = selected.iter().position(|w| w.folder == world.folder);
+        let mut checked = position.is_some();
+        ui.horizontal(|ui| {
+            if let Some(icon) = &world.icon {
+                ui.add(Image::new(format!("file://{}", icon.to_string_lossy())).max_size(Vec2::splat(APP_TABLE_ROW_HEIGHT)));
+            }
+            let last_played = format_time_ago(world.last_played.and_then(|t| u64::try_from(t).ok()), now);
+            if ui.checkbox(&mut checked, &world.name).on_hover_text(format!("{}\nLast played: {}", world.folder, last_played)).changed() {
+                match position {
+                    Some(i) => { selected.remove(i); }
+                    None => selected.push(WorldShortcut { folder: world.folder.clone(), name: world.name.clone() }),
+                }
+            }
+        });
+    }
+
+    // Worlds that were deleted or renamed since they were picked.
+    let mut remove = None;
+    for (i, world) in selected.iter().enumerate().filter(|(_, w)| !found.iter().any(|f| f.folder == w.folder)) {
+        ui.horizontal(|ui| {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {} (missing)", world.name));
+            if ui.small_button("🗑").clicked() { remove = Some(i); }
+        });
+    }
+    if let Some(i) = remove { selected.remove(i); }
+    rescan
+}
+
+fn image_picker(ui: &mut Ui, label: &str, path: &mut Option<PathBuf>) {
+    ui.horizontal(|ui| {
+        ui.label(format!("{}:", label));
+        match path {
+            Some(p) => { ui.label(p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()).on_hover_text(p.to_string_lossy()); }
+            None => { ui.weak("Default"); }
+        }
+        if ui.small_button("📂").clicked() {
+            if let Some(file) = rfd::FileDialog::new().add_filter("Images", &IMAGE_EXTENSIONS).pick_file() {
+                *path = Some(file);
+            }
+        }
+        if path.is_some() && ui.small_button("✖").clicked() {
+            *path = None;
+        }
+    });
+}
+
+fn status_badge(ui: &mut Ui, status: &SyncStatus) {
+    match status {
+        SyncStatus::NotExported => { ui.weak(status.label()); }
+        SyncStatus::Exported => { ui.colored_label(Color32::from_rgb(90, 170, 90), format!("✔ {}", status.label())); }
+        SyncStatus::OutOfDate(reason) => {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", status.label())).on_hover_text(reason);
+        }
+    }
+}
+
+/// Shortcuts Opal made for instances that don't exist anymore.
+fn orphans_ui(ui: &mut Ui, model: &AppModel) {
+    if model.orphaned_shortcuts.is_empty() { return; }
+    CollapsingHeader::new(format!("⚠ {} Orphaned Shortcuts", model.orphaned_shortcuts.len()))
+        .id_salt("orphaned_shortcuts")
+        .show(ui, |ui| {
+            ui.weak("These Steam shortcuts launch PrismLauncher instances that no longer exist.");
+            for shortcut in model.orphaned_shortcuts.iter() {
+                ui.label(format!("{} ({})", shortcut.app_name, shortcut.launch_options));
+            }
+        });
+}
+
+/// "All / None / Invert" buttons. Returns the one that was clicked, if any.
+fn select_buttons(ui: &mut Ui) -> Option<SelectAction> {
+    let mut action = None;
+    if ui.small_button("All").clicked() { action = Some(SelectAction::All); }
+    if ui.small_button("None").clicked() { action = Some(SelectAction::None); }
+    if ui.small_button("Invert").clicked() { action = Some(SelectAction::Invert); }
+    action
+}
+
+/// Rule editor plus a live preview of the instances the rules select.
+fn rules_ui(ui: &mut Ui, model: &mut AppModel, compiled: &mut Option<CompiledRules>) {
+    let current = compiled_rules(compiled, &model.config.selection_rules);
+    let errors: Vec<Option<String>> = (0..model.config.selection_rules.rules.len())
+        .map(|i| current.error(i).map(String::from))
+        .collect();
+    let rules = &mut model.config.selection_rules;
+    let mut remove = None;
+    let mut swap = None;
+
+    for (i, rule) in rules.rules.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut rule.enabled, "");
+                ComboBox::from_id_salt("action")
+                    .selected_text(format!("{:?}", rule.action))
+                    .width(80.0)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut rule.action, RuleAction::Include, "Include");
+                        ui.selectable_value(&mut rule.action, RuleAction::Exclude, "Exclude");
+                    });
+                ComboBox::from_id_salt("condition")
+                    .selected_text(rule.condition.kind_name())
+                    .show_ui(ui, |ui| {
+                        for kind in RuleCondition::kinds() {
+                            let selected = std::mem::discriminant(&kind) == std::mem::discriminant(&rule.condition);
+                            if ui.selectable_label(selected, kind.kind_name()).clicked() && !selected {
+                                rule.condition = kind;
+                            }
+                        }
+                    });
+                condition_value_ui(ui, &mut rule.condition);
+
+                if let Some(e) = &errors[i] {
+                    ui.colored_label(ui.visuals().error_fg_color, "⚠").on_hover_text(e.as_str());
+                }
+                if ui.small_button("⏶").clicked() && i > 0 { swap = Some(i); }
+                if ui.small_button("🗑").clicked() { remove = Some(i); }
+            });
+        });
+    }
+    if let Some(i) = swap { rules.rules.swap(i - 1, i); }
+    if let Some(i) = remove { rules.rules.remove(i); }
+
+    ui.horizontal(|ui| {
+        if ui.button("Add Rule").clicked() {
+            rules.rules.push(SelectionRule::default());
+        }
+        if ui.button("Apply Rules").on_hover_text("Check exactly the instances the rules select").clicked() {
+            rules.apply(&mut model.instances);
+        }
+    });
+
+    preview_ui(ui, model, compiled_rules(compiled, &model.config.selection_rules));
+}
+
+/// `rules` compiled, reusing `compiled` unless the rules were edited since.
+fn compiled_rules<'a>(compiled: &'a mut Option<CompiledRules>, rules: &RuleSet) -> &'a CompiledRules {
+    if compiled.as_ref().is_some_and(|c| !c.is_for(rules)) {
+        *compiled = None;
+    }
+    compiled.get_or_insert_with(|| rules.compile())
+}
+
+fn condition_value_ui(ui: &mut Ui, condition: &mut RuleCondition) {
+    match condition {
+        RuleCondition::Everything => {}
+        RuleCondition::Group(text) | RuleCondition::NameGlob(text) | RuleCondition::NameRegex(text) => {
+            ui.add(TextEdit::singleline(text).desired_width(APP_RULE_TEXT_WIDTH));
+        }
+        RuleCondition::Loader(loader) => {
+            ComboBox::from_id_salt("loader")
+                .selected_text(loader.name())
+                .show_ui(ui, |ui| {
+                    for l in ModLoader::ALL {
+                        ui.selectable_value(loader, l, l.name());
+                    }
+                });
+        }
+        RuleCondition::VersionRange { min, max } => {
+            ui.add(TextEdit::singleline(min).hint_text("any").desired_width(60.0));
+            ui.label("to");
+            ui.add(TextEdit::singleline(max).hint_text("any").desired_width(60.0));
+        }
+        RuleCondition::LaunchedWithinDays(days) => {
+            ui.add(DragValue::new(days).range(0..=3650).suffix(" days"));
+        }
+    }
+}
 
+fn preview_ui(ui: &mut Ui, model: &AppModel, rules: &CompiledRules) {
+    if model.config.selection_rules.is_empty() {
+        ui.label("No rules yet: instances are picked by hand.");
+        return;
     }
-}
\ No newline at end of file
+    let now = now_ms();
+    let matching: Vec<&str> = model.instances.iter()
+        .filter(|inst| rules.matches(inst, now))
+        .map(|inst| inst.display_name())
+        .collect();
+    ui.label(format!("{} of {} instances match: {}", matching.len(), model.instances.len(), matching.join(", ")));
+}
@@ -0,0 +1,158 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use super::merge::ShortcutField;
+use super::platform::FileIo;
+use super::rules::now_ms;
+use super::steam::DesiredShortcut;
+
+const HISTORY_DIR : &str = "history";
+const HISTORY_FILE : &str = "exports.json";
+/// Older exports are forgotten, along with their snapshots.
+const KEPT_EXPORTS : usize = 10;
+
+/// A shortcut an export meant to write. The next export uses it to tell what Opal wrote
+/// from what the user changed in Steam since.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct PlannedShortcut {
+    /// The app_id the shortcut ended up with. 0 until it's written.
+    pub app_id: u32,
+    pub app_name: String,
+    pub exe: String,
+    pub launch_options: String,
+    pub tags: Vec<String>,
+    /// The fields the instance set in Opal.
+    pub explicit: Vec<ShortcutField>,
+}
+
+/// One export to Steam, with what is needed to take it back.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ExportRecord {
+    /// Milliseconds since the epoch.
+    pub time: u64,
+    pub shortcuts_path: PathBuf,
+    /// Copy of `shortcuts.vdf` from before the export, in the history folder. `None` if
+    /// there was no `shortcuts.vdf` yet.
+    pub snapshot: Option<String>,
+    pub plan: Vec<PlannedShortcut>,
+    /// Artwork the export added to the grid folder.
+    pub created_files: Vec<PathBuf>,
+    /// The file contents until the record is saved, so failed exports leave nothing behind.
+    #[serde(skip)]
+    snapshot_bytes: Option<Vec<u8>>,
+}
+impl ExportRecord {
+    /// Remember the current `shortcuts.vdf`, before it's written.
+    pub fn begin(files: &dyn FileIo, shortcuts_path: &Path, desired: &[DesiredShortcut]) -> io::Result<Self> {
+        let snapshot_bytes = match files.read(shortcuts_path) {
+            Ok(bytes) => Some(bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            time: now_ms(),
+            shortcuts_path: shortcuts_path.to_path_buf(),
+            snapshot: None,
+            plan: desired.iter()
+                .map(|d| PlannedShortcut {
+                    app_id: 0,
+                    app_name: d.app_name.clone(),
+                    exe: d.exe.clone(),
+                    launch_options: d.launch_options.clone(),
+                    tags: d.tags.clone(),
+                    explicit: d.explicit.clone(),
+                })
+                .collect(),
+            created_files: Vec::new(),
+            snapshot_bytes,
+        })
+    }
+}
+
+/// The last exports, oldest first, kept in Opal's data folder (`data_dir` below).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ExportHistory {
+    pub exports: Vec<ExportRecord>,
+}
+impl ExportHistory {
+    fn dir(data_dir: &Path) -> PathBuf {
+        data_dir.join(HISTORY_DIR)
+    }
+
+    /// Load the history, or an empty one if there is none yet.
+    pub fn load(data_dir: &Path) -> io::Result<Self> {
+        match std::fs::read_to_string(Self::dir(data_dir).join(HISTORY_FILE)) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn save(&self, data_dir: &Path) -> io::Result<()> {
+        let dir = Self::dir(data_dir);
+        std::fs::create_dir_all(&dir)?;
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(dir.join(HISTORY_FILE), json)
+    }
+
+    /// Add a finished export, writing its snapshot, and forget the oldest ones.
+    pub fn record(data_dir: &Path, mut record: ExportRecord) -> io::Result<()> {
+        let dir = Self::dir(data_dir);
+        let mut history = Self::load(data_dir)?;
+        if let Some(bytes) = record.snapshot_bytes.take() {
+            std::fs::create_dir_all(&dir)?;
+            let name = format!("shortcuts-{}.vdf", record.time);
+            std::fs::write(dir.join(&name), bytes)?;
+            record.snapshot = Some(name);
+        }
+        history.exports.push(record);
+
+        let excess = history.exports.len().saturating_sub(KEPT_EXPORTS);
+        for old in history.exports.drain(..excess) {
+            if let Some(name) = old.snapshot {
+                if let Err(e) = std::fs::remove_file(dir.join(name)) { warn!("Couldn't delete an old snapshot: {}", e); }
+            }
+        }
+        history.save(data_dir)
+    }
+
+    pub fn last(&self) -> Option<&ExportRecord> {
+        self.exports.last()
+    }
+
+    /// Put `shortcuts.vdf` back the way it was before the last export and delete the artwork
+    /// that export added. Steam has to be closed. Steam Play tools and collections are left
+    /// alone; entries for shortcuts that are gone don't do anything. Returns the undone export.
+    pub fn undo_last(data_dir: &Path, files: &dyn FileIo) -> io::Result<ExportRecord> {
+        let dir = Self::dir(data_dir);
+        let mut history = Self::load(data_dir)?;
+        let Some(record) = history.exports.pop() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "There is no export to undo"));
+        };
+
+        // Only once shortcuts.vdf is back is the artwork of no use any more.
+        match &record.snapshot {
+            Some(name) => files.write(&record.shortcuts_path, &std::fs::read(dir.join(name))?)?,
+            None => match files.remove(&record.shortcuts_path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            },
+        }
+        for file in record.created_files.iter() {
+            match files.remove(file) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => warn!("Couldn't delete {}: {}", file.display(), e),
+                _ => {}
+            }
+        }
+        if let Some(name) = &record.snapshot {
+            std::fs::remove_file(dir.join(name))?;
+        }
+        history.save(data_dir)?;
+        info!("Undid the export of {} shortcuts", record.plan.len());
+        Ok(record)
+    }
+}
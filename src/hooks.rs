@@ -0,0 +1,193 @@
+use std::io;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use log::info;
+use serde::{Serialize, Deserialize};
+use super::instances::Instance;
+use super::servers::game_dir;
+
+const POLL_INTERVAL : Duration = Duration::from_millis(100);
+
+/// What `opal launch` does when a hook fails or times out.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookFailure {
+    /// Don't start the game. After the session, the same as `Warn`.
+    Abort,
+    Warn,
+    Ignore,
+}
+impl HookFailure {
+    pub const ALL : [HookFailure; 3] = [HookFailure::Abort, HookFailure::Warn, HookFailure::Ignore];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HookFailure::Abort => "Abort the launch",
+            HookFailure::Warn => "Warn",
+            HookFailure::Ignore => "Ignore",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    PreLaunch,
+    PostExit,
+}
+impl HookStage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HookStage::PreLaunch => "pre-launch",
+            HookStage::PostExit => "post-exit",
+        }
+    }
+}
+
+/// Shell commands `opal launch` runs around a session, e.g. to sync worlds with another machine.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Hooks {
+    pub pre_launch: String,
+    pub post_exit: String,
+    /// A hook that runs longer is stopped and counts as failed.
+    pub timeout_secs: u64,
+    pub on_failure: HookFailure,
+}
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            pre_launch: String::new(),
+            post_exit: String::new(),
+            timeout_secs: 120,
+            on_failure: HookFailure::Warn,
+        }
+    }
+}
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre_launch.trim().is_empty() && self.post_exit.trim().is_empty()
+    }
+
+    pub fn command(&self, stage: HookStage) -> &str {
+        match stage {
+            HookStage::PreLaunch => self.pre_launch.trim(),
+            HookStage::PostExit => self.post_exit.trim(),
+        }
+    }
+}
+
+/// Run the hook for `stage` in the instance folder, if there is one. The command goes through
+/// the system shell and gets the instance in `OPAL_*` environment variables; after the session
+/// also its length in `OPAL_SESSION_SECS`. Exiting with an error code counts as failing.
+pub fn run_hook(hooks: &Hooks, stage: HookStage, inst: &Instance, session_secs: Option<u64>) -> io::Result<()> {
+    let command = hooks.command(stage);
+    if command.is_empty() { return Ok(()); }
+    info!("Running the {} hook of {}: {}", stage.label(), inst.folder_name, command);
+
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    };
+    shell.current_dir(&inst.path)
+        .env("OPAL_HOOK", stage.label())
+        .env("OPAL_INSTANCE", &inst.folder_name)
+        .env("OPAL_INSTANCE_NAME", inst.display_name())
+        .env("OPAL_INSTANCE_PATH", &inst.path)
+        .env("OPAL_GAME_DIR", game_dir(&inst.path).unwrap_or_else(|| inst.path.clone()))
+        .env("OPAL_GROUP", &inst.group)
+        .env("OPAL_MC_VERSION", inst.mc_version.as_deref().unwrap_or_default());
+    if let Some(secs) = session_secs {
+        shell.env("OPAL_SESSION_SECS", secs.to_string());
+    }
+
+    process_tree::isolate(&mut shell);
+    let mut child = shell.spawn()?;
+    let tree = process_tree::ProcessTree::of(&child)?;
+    let timeout = Duration::from_secs(hooks.timeout_secs);
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if status.success() { return Ok(()); }
+            return Err(io::Error::other(format!("the {} hook failed ({})", stage.label(), status)));
+        }
+        if started.elapsed() >= timeout {
+            // Killing just the shell would leave whatever the script started running.
+            tree.kill()?;
+            child.wait()?;
+            return Err(io::Error::new(io::ErrorKind::TimedOut,
+                format!("the {} hook didn't finish in {} s", stage.label(), hooks.timeout_secs)));
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
+
+/// A hook's shell and every process it starts, so a timeout can stop all of them: a process
+/// group on Unix, a job object on Windows.
+#[cfg(unix)]
+mod process_tree {
+    use std::io;
+    use std::os::unix::process::CommandExt;
+    use std::process::{Child, Command};
+
+    /// Start the command in a process group of its own, which its children inherit.
+    pub fn isolate(command: &mut Command) {
+        command.process_group(0);
+    }
+
+    pub struct ProcessTree(libc::pid_t);
+    impl ProcessTree {
+        pub fn of(child: &Child) -> io::Result<Self> {
+            libc::pid_t::try_from(child.id()).map(Self).map_err(io::Error::other)
+        }
+
+        pub fn kill(&self) -> io::Result<()> {
+            // The group id is the shell's pid, see `isolate`.
+            match unsafe { libc::killpg(self.0, libc::SIGKILL) } {
+                0 => Ok(()),
+                _ => Err(io::Error::last_os_error()),
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod process_tree {
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::{Child, Command};
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{AssignProcessToJobObject, CreateJobObjectW, TerminateJobObject};
+
+    /// Processes started by a process in a job are in the job too, so there's nothing to set up.
+    pub fn isolate(_command: &mut Command) {}
+
+    pub struct ProcessTree(HANDLE);
+    impl ProcessTree {
+        pub fn of(child: &Child) -> io::Result<Self> {
+            let job = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+            if job.is_null() { return Err(io::Error::last_os_error()); }
+            let tree = Self(job);
+            if unsafe { AssignProcessToJobObject(job, child.as_raw_handle() as HANDLE) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(tree)
+        }
+
+        pub fn kill(&self) -> io::Result<()> {
+            match unsafe { TerminateJobObject(self.0, 1) } {
+                0 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        }
+    }
+    impl Drop for ProcessTree {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0); }
+        }
+    }
+}
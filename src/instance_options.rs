@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
+use super::backup::BackupSettings;
+use super::compat::CompatTool;
+use super::controller::ControllerLayout;
+use super::hooks::Hooks;
+use super::launch::AlreadyRunning;
+use super::merge::ShortcutField;
+use super::servers::Server;
+use super::worlds::WorldShortcut;
+
+/// Custom Steam library images for a shortcut. See `artwork::install_artwork`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Artwork {
+    /// Portrait capsule (600x900).
+    pub grid: Option<PathBuf>,
+    /// Wide capsule (920x430).
+    pub wide: Option<PathBuf>,
+    pub hero: Option<PathBuf>,
+    pub logo: Option<PathBuf>,
+}
+impl Artwork {
+    pub fn is_empty(&self) -> bool {
+        *self == Artwork::default()
+    }
+}
+
+/// Per-instance overrides for the Steam shortcut, stored in the config by folder name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct InstanceOptions {
+    pub hidden: bool,
+    pub allow_overlay: bool,
+    pub allow_desktop_config: bool,
+    pub open_vr: bool,
+    /// Added after Opal's own `-l <instance>`.
+    pub extra_launch_options: String,
+    pub extra_tags: Vec<String>,
+    pub icon: Option<PathBuf>,
+    pub artwork: Artwork,
+    /// Each gets its own quick-join shortcut next to the instance's.
+    pub servers: Vec<Server>,
+    /// Worlds that get a shortcut that loads straight into them.
+    pub worlds: Vec<WorldShortcut>,
+    /// PrismLauncher profile names (`-a <profile>`). With more than one, every shortcut is
+    /// exported once per account. Empty uses Prism's default account.
+    pub accounts: Vec<String>,
+    /// Steam Input template. Detected from the instance's mods if not set.
+    pub controller_layout: Option<ControllerLayout>,
+    /// Steam Play tool for the shortcuts. Uses the one from the settings if not set.
+    pub compat_tool: Option<CompatTool>,
+    /// Commands run around `opal launch`. Uses the group's hooks if empty.
+    pub hooks: Hooks,
+    /// World backups before `opal launch`. Uses the ones from the settings if not set.
+    pub backup: Option<BackupSettings>,
+    /// What a second launch does while the game runs. Uses the one from the settings if not set.
+    pub already_running: Option<AlreadyRunning>,
+}
+impl Default for InstanceOptions {
+    fn default() -> Self {
+        Self {
+            hidden: false,
+            allow_overlay: true,
+            allow_desktop_config: true,
+            open_vr: false,
+            extra_launch_options: String::new(),
+            extra_tags: Vec::new(),
+            icon: None,
+            artwork: Artwork::default(),
+            servers: Vec::new(),
+            worlds: Vec::new(),
+            accounts: Vec::new(),
+            controller_layout: None,
+            compat_tool: None,
+            hooks: Hooks::default(),
+            backup: None,
+            already_running: None,
+        }
+    }
+}
+impl InstanceOptions {
+    /// The shortcut fields this instance sets to something other than the default.
+    pub fn explicit_fields(&self) -> Vec<ShortcutField> {
+        let defaults = InstanceOptions::default();
+        let mut fields = Vec::new();
+        if self.hidden != defaults.hidden { fields.push(ShortcutField::Hidden); }
+        if self.allow_overlay != defaults.allow_overlay { fields.push(ShortcutField::AllowOverlay); }
+        if self.allow_desktop_config != defaults.allow_desktop_config { fields.push(ShortcutField::AllowDesktopConfig); }
+        if self.open_vr != defaults.open_vr { fields.push(ShortcutField::OpenVr); }
+        if self.icon.is_some() { fields.push(ShortcutField::Icon); }
+        fields
+    }
+}
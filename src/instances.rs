@@ -1,31 +1,104 @@
-use std::{default, io};
+use std::io;
 use std::path::{Path, PathBuf};
-use std::collections::{HashMap};
-use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
 use directories::BaseDirs;
+use super::sync::SyncStatus;
 
-#[derive(Debug)]
+pub const INSTANCE_CFG_FILE : &str = "instance.cfg";
+const MMC_PACK_FILE : &str = "mmc-pack.json";
+
+/// Mod loader of an instance, taken from the components in `mmc-pack.json`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModLoader {
+    #[default]
+    Vanilla,
+    Forge,
+    NeoForge,
+    Fabric,
+    Quilt,
+}
+impl ModLoader {
+    pub const ALL : [ModLoader; 5] = [
+        ModLoader::Vanilla, ModLoader::Forge, ModLoader::NeoForge, ModLoader::Fabric, ModLoader::Quilt,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ModLoader::Vanilla => "Vanilla",
+            ModLoader::Forge => "Forge",
+            ModLoader::NeoForge => "NeoForge",
+            ModLoader::Fabric => "Fabric",
+            ModLoader::Quilt => "Quilt",
+        }
+    }
+
+    fn from_component_uid(uid: &str) -> Option<ModLoader> {
+        match uid {
+            "net.minecraftforge" => Some(ModLoader::Forge),
+            "net.neoforged" => Some(ModLoader::NeoForge),
+            "net.fabricmc.fabric-loader" => Some(ModLoader::Fabric),
+            "org.quiltmc.quilt-loader" => Some(ModLoader::Quilt),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct Instance {
   pub folder_name : String,
   pub group : String,
+  #[allow(dead_code)] // Not resolved from Prism's icon keys yet.
   pub icon_path : Option<PathBuf>,
   pub checked : bool,
+  /// Whether a shortcut for this instance is already in Steam.
+  pub status : SyncStatus,
+
+  // Metadata read from the instance folder. Everything is optional because
+  // Prism only writes these files once the instance has been set up.
+  pub path : PathBuf,
+  pub name : String,
+  pub mc_version : Option<String>,
+  pub loader : ModLoader,
+  pub loader_version : Option<String>,
+  /// Milliseconds since the UNIX epoch, as written by Prism.
+  pub last_launch : Option<u64>,
+  /// Seconds.
+  pub total_time_played : u64,
 }
-impl Default for Instance {
-    fn default() -> Self {
-        Self {
-            folder_name : String::new(),
-            group : String::new(),
-            icon_path : None,
-            checked : false
+impl Instance {
+    /// Name shown to the user: Prism's display name, falling back to the folder name.
+    pub fn display_name(&self) -> &str {
+        if self.name.is_empty() { &self.folder_name } else { &self.name }
+    }
+
+    /// Fill in the metadata from `instance.cfg` and `mmc-pack.json`. Missing or
+    /// malformed files are not an error, the fields are simply left empty.
+    pub fn read_metadata(&mut self) {
+        if let Ok(cfg) = std::fs::read_to_string(self.path.join(INSTANCE_CFG_FILE)) {
+            let cfg = parse_instance_cfg(&cfg);
+            if let Some(name) = cfg.get("name") { self.name = name.clone(); }
+            self.last_launch = cfg.get("lastLaunchTime").and_then(|v| v.parse().ok()).filter(|t| *t > 0);
+            self.total_time_played = cfg.get("totalTimePlayed").and_then(|v| v.parse().ok()).unwrap_or(0);
+        }
+
+        if let Ok(pack) = std::fs::read_to_string(self.path.join(MMC_PACK_FILE)) {
+            if let Ok(pack) = serde_json::from_str::<MmcPack>(&pack) {
+                for component in pack.components {
+                    if component.uid == "net.minecraft" {
+                        self.mc_version = component.version;
+                    } else if let Some(loader) = ModLoader::from_component_uid(&component.uid) {
+                        self.loader = loader;
+                        self.loader_version = component.version;
+                    }
+                }
+            }
         }
     }
 }
 
 #[derive(Debug, Deserialize)]
 struct Root {
-    #[serde(rename = "formatVersion")]
-    format_version: String,
     groups: HashMap<String, Group>,
 }
 
@@ -35,28 +108,89 @@ struct Group {
     instances: Vec<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct MmcPack {
+    components: Vec<MmcComponent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MmcComponent {
+    uid: String,
+    version: Option<String>,
+}
+
+/// `instance.cfg` is a flat INI file. We only care about the `key=value` lines.
+pub fn parse_instance_cfg(contents: &str) -> HashMap<String, String> {
+    contents.lines()
+        .filter(|line| !line.starts_with('[') && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect()
+}
+
+/// Set `values` in an `instance.cfg`, keeping every other line as it is.
+/// Keys that aren't in the file yet are added to the `[General]` section.
+pub fn update_instance_cfg(path: &Path, values: &[(&str, String)]) -> std::io::Result<()> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+    for (key, value) in values {
+        let line = format!("{}={}", key, value);
+        match lines.iter().position(|l| l.split_once('=').is_some_and(|(k, _)| k.trim() == *key)) {
+            Some(i) => lines[i] = line,
+            None => {
+                let general = lines.iter().position(|l| l.trim() == "[General]").map(|i| i + 1).unwrap_or(0);
+                lines.insert(general, line);
+            }
+        }
+    }
+    std::fs::write(path, lines.join("\n") + "\n")
+}
+
 /// Parse the JSON and build instances. Set `include_hidden` to false to skip hidden groups.
+/// Instances that aren't in any group only exist as folders, so those are picked up from
+/// the instances directory with an empty `group`.
+/// The result is sorted by group, then name, so it doesn't reshuffle between refreshes.
 pub fn get_instances_from_path(path: impl AsRef<Path>, include_hidden: bool)
 -> Result<Vec<Instance>, Box<dyn std::error::Error>> {
+  let path = path.as_ref();
   let json_file = std::fs::read_to_string(path)?;
   let root: Root = serde_json::from_str(&json_file)?;
+  let instances_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
 
   let mut instances = Vec::new();
+  let mut grouped = HashSet::new();
 
   for (group_name, group) in root.groups.into_iter() {
+    grouped.extend(group.instances.iter().cloned());
     if !include_hidden && group.hidden { continue; }
     else { 
       for folder_name in group.instances.into_iter() {
           instances.push(Instance {
+            path : instances_dir.join(&folder_name),
             folder_name, group : group_name.clone(), ..Default::default()
-        });
+          });
       }
     }
   } // I know this can be done better, but who's gonna learn closures man
 
+  if let Ok(entries) = std::fs::read_dir(&instances_dir) {
+    for entry in entries.flatten() {
+      let folder_name = entry.file_name().to_string_lossy().to_string();
+      if grouped.contains(&folder_name) || !entry.path().join(INSTANCE_CFG_FILE).is_file() { continue; }
+      instances.push(Instance { path : entry.path(), folder_name, ..Default::default() });
+    }
+  }
+
+  for instance in instances.iter_mut() {
+    instance.read_metadata();
+  }
+  instances.sort_by_cached_key(|i| (i.group.is_empty(), i.group.to_lowercase(), i.display_name().to_lowercase()));
+
   Ok(instances)
 }
 
+// Each platform's block returns on its own.
+#[allow(clippy::needless_return)]
 pub fn default_prism_path() -> Result<PathBuf, io::Error> {
 
     #[cfg(target_os = "windows")]
@@ -71,4 +205,16 @@ pub fn default_prism_path() -> Result<PathBuf, io::Error> {
         }
     }
 
-}
\ No newline at end of file
+    #[cfg(not(target_os = "windows"))]
+    {
+        if let Some(base_dirs) = BaseDirs::new() {
+            let mut data_path = base_dirs.data_dir().to_owned();
+            data_path.push("PrismLauncher");
+
+            return Ok(data_path)
+        } else {
+            return Err(io::Error::last_os_error())
+        }
+    }
+
+}
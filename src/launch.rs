@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use super::app::AppModel;
+use super::backup::BackupSettings;
+use super::hooks::Hooks;
+use super::instances::{parse_instance_cfg, update_instance_cfg, Instance, INSTANCE_CFG_FILE};
+use super::playtime::{PlaytimeDb, Session};
+use super::rules::now_ms;
+use super::steam::edit_steam_shortcuts;
+use super::sync::launched_instance;
+
+const POLL_INTERVAL : Duration = Duration::from_secs(1);
+const JAVA_NAMES : [&str; 4] = ["java", "javaw", "java.exe", "javaw.exe"];
+const PRISMLAUNCHER_PROCESS_NAMES : [&str; 2] = ["prismlauncher", "prismlauncher.exe"];
+
+/// What `opal launch` does when the instance is already running, e.g. after pressing Play twice.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlreadyRunning {
+    /// Bring the game's window to the front.
+    Focus,
+    /// Show a message and don't launch.
+    Refuse,
+}
+impl AlreadyRunning {
+    pub const ALL : [AlreadyRunning; 2] = [AlreadyRunning::Focus, AlreadyRunning::Refuse];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AlreadyRunning::Focus => "Switch to the game",
+            AlreadyRunning::Refuse => "Show a message",
+        }
+    }
+}
+
+/// How `opal launch` is used.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct LaunchSettings {
+    /// Export shortcuts that run `opal launch` instead of PrismLauncher, so play time is recorded.
+    pub use_wrapper: bool,
+    /// Also add each session to the play time PrismLauncher shows.
+    pub sync_prism_play_time: bool,
+    /// How long to wait for the game to show up. Prism may download updates first.
+    pub start_timeout_secs: u64,
+    /// Keyed by PrismLauncher group, for instances that don't have hooks of their own.
+    pub group_hooks: HashMap<String, Hooks>,
+    /// World backups, for instances that don't have their own settings.
+    pub backup: BackupSettings,
+    /// For instances that don't have their own setting.
+    pub already_running: AlreadyRunning,
+}
+impl Default for LaunchSettings {
+    fn default() -> Self {
+        Self {
+            use_wrapper: false,
+            sync_prism_play_time: false,
+            start_timeout_secs: 600,
+            group_hooks: HashMap::new(),
+            backup: BackupSettings::default(),
+            already_running: AlreadyRunning::Focus,
+        }
+    }
+}
+
+/// The instance folder in PrismLauncher arguments (`-l <instance>`).
+pub fn instance_argument(prism_args: &[String]) -> Option<&str> {
+    let position = prism_args.iter().position(|a| a == "-l" || a == "--launch")?;
+    prism_args.get(position + 1).map(String::as_str)
+}
+
+/// How the game's process ended, as far as `opal launch` could see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameExit {
+    Code(i32),
+    /// Killed by a Unix signal.
+    Signal(i32),
+    /// The game is Prism's child, not Opal's, so its exit status can't always be read:
+    /// on Linux only once Prism closed while the game ran, on other Unixes never.
+    Unknown,
+}
+impl GameExit {
+    /// Whether the game ended in a way quitting it doesn't. Being stopped, like Steam's
+    /// "Exit game" does, isn't abnormal; the JVM exits with 128 + the signal then.
+    pub fn is_abnormal(&self) -> bool {
+        const STOP_SIGNALS : [i32; 4] = [1, 2, 9, 15];
+        match self {
+            GameExit::Code(0) | GameExit::Unknown => false,
+            GameExit::Code(code) if cfg!(unix) => !STOP_SIGNALS.iter().any(|s| *code == 128 + s),
+            GameExit::Code(_) => true,
+            GameExit::Signal(signal) => !STOP_SIGNALS.contains(signal),
+        }
+    }
+}
+impl std::fmt::Display for GameExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameExit::Code(code) => write!(f, "exit code {}", code),
+            GameExit::Signal(signal) => write!(f, "signal {}", signal),
+            GameExit::Unknown => write!(f, "unknown exit status"),
+        }
+    }
+}
+
+/// Start PrismLauncher with `prism_args`, wait for the game to start and then to exit,
+/// and return the session and how the game ended. Prism hands the game off to its own process
+/// (or to a Prism that was already open), so the game is found by its Java process rather
+/// than as a child.
+pub fn run_session(model: &AppModel, inst: &Instance, prism_args: &[String]) -> io::Result<(Session, GameExit)> {
+    info!("Launching {} with {:?}", inst.folder_name, prism_args);
+    game_exit::adopt_orphans();
+    Command::new(model.prism_exe_path())
+        .args(prism_args)
+        .current_dir(&model.config.prism_main_path)
+        .spawn()?;
+
+    let mut system = System::new();
+    let timeout = Duration::from_secs(model.config.launch.start_timeout_secs);
+    let waiting_since = Instant::now();
+    let pid = loop {
+        refresh(&mut system);
+        if let Some(pid) = find_game_process(&system, &inst.path) { break pid; }
+        // Give Prism a moment to start before taking its absence as it being closed.
+        if waiting_since.elapsed() > POLL_INTERVAL * 5 && !is_running(&system, &PRISMLAUNCHER_PROCESS_NAMES) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "PrismLauncher closed before the game started"));
+        }
+        if waiting_since.elapsed() >= timeout {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "The game didn't start in time"));
+        }
+        sleep(POLL_INTERVAL);
+    };
+
+    let started = now_ms();
+    info!("Game started (PID {})", pid);
+    let mut watcher = game_exit::ExitWatcher::new(pid.as_u32());
+    let pids = [pid];
+    let exit = loop {
+        if let Some(exit) = watcher.reap() { break exit; }
+        if system.refresh_processes(ProcessesToUpdate::Some(&pids), true) == 0 || system.process(pid).is_none() {
+            break watcher.finish();
+        }
+        sleep(POLL_INTERVAL);
+    };
+    let duration_secs = now_ms().saturating_sub(started) / 1000;
+    info!("Game exited after {} s ({})", duration_secs, exit);
+
+    let session = Session {
+        instance: inst.folder_name.clone(),
+        name: inst.display_name().to_string(),
+        group: inst.group.clone(),
+        started,
+        duration_secs,
+    };
+    Ok((session, exit))
+}
+
+/// Reading the exit status of the game, which is Prism's child rather than Opal's.
+#[cfg(target_os = "linux")]
+mod game_exit {
+    use super::GameExit;
+
+    /// Become the game's parent if Prism closes while it runs (e.g. with "Close the launcher
+    /// after the game window opens"), so its exit status can be read.
+    pub fn adopt_orphans() {
+        unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0); }
+    }
+
+    pub struct ExitWatcher(libc::pid_t);
+    impl ExitWatcher {
+        pub fn new(pid: u32) -> Self {
+            Self(pid as libc::pid_t)
+        }
+
+        /// The exit status, once the game exited as Opal's child.
+        pub fn reap(&mut self) -> Option<GameExit> {
+            let mut status = 0;
+            if unsafe { libc::waitpid(self.0, &mut status, libc::WNOHANG) } != self.0 { return None; }
+            if libc::WIFEXITED(status) { return Some(GameExit::Code(libc::WEXITSTATUS(status))); }
+            if libc::WIFSIGNALED(status) { return Some(GameExit::Signal(libc::WTERMSIG(status))); }
+            None
+        }
+
+        /// The game is gone without having been Opal's child, Prism collected its status.
+        pub fn finish(mut self) -> GameExit {
+            self.reap().unwrap_or(GameExit::Unknown)
+        }
+    }
+}
+
+#[cfg(windows)]
+mod game_exit {
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+    use super::GameExit;
+
+    /// Windows lets any process read the exit code of another it holds a handle to.
+    pub fn adopt_orphans() {}
+
+    pub struct ExitWatcher(HANDLE);
+    impl ExitWatcher {
+        /// Opened while the game runs, so the exit code is still there once it's gone.
+        pub fn new(pid: u32) -> Self {
+            Self(unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) })
+        }
+
+        pub fn reap(&mut self) -> Option<GameExit> {
+            None
+        }
+
+        pub fn finish(self) -> GameExit {
+            let mut code = 0u32;
+            if self.0.is_null() || unsafe { GetExitCodeProcess(self.0, &mut code) } == 0 {
+                return GameExit::Unknown;
+            }
+            GameExit::Code(code as i32)
+        }
+    }
+    impl Drop for ExitWatcher {
+        fn drop(&mut self) {
+            if !self.0.is_null() { unsafe { CloseHandle(self.0); } }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod game_exit {
+    use super::GameExit;
+
+    pub fn adopt_orphans() {}
+
+    pub struct ExitWatcher;
+    impl ExitWatcher {
+        pub fn new(_pid: u32) -> Self { Self }
+        pub fn reap(&mut self) -> Option<GameExit> { None }
+        pub fn finish(self) -> GameExit { GameExit::Unknown }
+    }
+}
+
+fn refresh(system: &mut System) {
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true,
+        ProcessRefreshKind::nothing().with_cmd(UpdateKind::OnlyIfNotSet).with_cwd(UpdateKind::OnlyIfNotSet));
+}
+
+fn is_running(system: &System, names: &[&str]) -> bool {
+    system.processes().values().any(|p| names.iter().any(|n| p.name().eq_ignore_ascii_case(n)))
+}
+
+/// The Java process running the instance at `instance_path`: Prism starts it in the
+/// instance's game folder, and its natives and libraries are in the instance folder.
+pub fn find_game_process(system: &System, instance_path: &Path) -> Option<Pid> {
+    system.processes().values()
+        .filter(|p| JAVA_NAMES.iter().any(|n| p.name().eq_ignore_ascii_case(n)))
+        .find(|p| p.cwd().is_some_and(|cwd| cwd.starts_with(instance_path))
+            || p.cmd().iter().any(|arg| names_path_in(&arg.to_string_lossy(), instance_path)))
+        .map(|p| p.pid())
+}
+
+/// Whether `arg` is a path in `dir`, or has one after `=` or in a class path. Paths are
+/// compared by whole components, so `instances/ATM9-1` isn't in `instances/ATM9`.
+fn names_path_in(arg: &str, dir: &Path) -> bool {
+    arg.split('=').flat_map(std::env::split_paths).any(|path| path.starts_with(dir))
+}
+
+/// The instance's game, if it is running right now.
+pub fn running_game(instance_path: &Path) -> Option<Pid> {
+    let mut system = System::new();
+    refresh(&mut system);
+    find_game_process(&system, instance_path)
+}
+
+pub fn is_game_running(instance_path: &Path) -> bool {
+    running_game(instance_path).is_some()
+}
+
+/// Try to bring the windows of a process to the front, with whatever the platform offers
+/// from the command line. Returns whether that worked.
+pub fn focus_process(pid: Pid) -> bool {
+    let pid = pid.to_string();
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command", &format!("(New-Object -ComObject WScript.Shell).AppActivate({})", pid)]);
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.args(["-e", &format!("tell application \"System Events\" to set frontmost of (first process whose unix id is {}) to true", pid)]);
+        command
+    } else {
+        let mut command = Command::new("xdotool");
+        command.args(["search", "--onlyvisible", "--pid", &pid, "windowactivate"]);
+        command
+    };
+    match command.output() {
+        // AppActivate reports failure as "False" rather than through the exit code.
+        Ok(output) => output.status.success() && !String::from_utf8_lossy(&output.stdout).trim().eq_ignore_ascii_case("false"),
+        Err(e) => {
+            warn!("Couldn't switch to the game: {}", e);
+            false
+        }
+    }
+}
+
+/// Store a finished session: in Opal's play time database, in the shortcuts' last play time
+/// and, if enabled, in PrismLauncher's own play time.
+pub fn record_session(model: &AppModel, inst: &Instance, session: &Session) -> io::Result<()> {
+    PlaytimeDb::record(model.data_dir()?, session.clone())?;
+
+    if model.config.launch.sync_prism_play_time {
+        // PrismLauncher may have counted the session itself, in which case its total grew
+        // since `inst` was read before the launch.
+        let cfg_path = inst.path.join(INSTANCE_CFG_FILE);
+        let cfg = parse_instance_cfg(&std::fs::read_to_string(&cfg_path)?);
+        let total: u64 = cfg.get("totalTimePlayed").and_then(|v| v.parse().ok()).unwrap_or(0);
+        if total == inst.total_time_played {
+            let values = [
+                ("totalTimePlayed", (total + session.duration_secs).to_string()),
+                ("lastTimePlayed", session.duration_secs.to_string()),
+                ("lastLaunchTime", session.started.to_string()),
+            ];
+            update_instance_cfg(&cfg_path, &values)?;
+        }
+    }
+
+    // Steam rewrites shortcuts.vdf when it exits, so while it runs the last play time is
+    // written by the next export instead.
+    if model.platform.steam.is_running() {
+        warn!("Steam is running, the last play time will be updated on the next export");
+        return Ok(());
+    }
+    let last_play_time = (session.ended() / 1000) as u32;
+    edit_steam_shortcuts(model.files(), &model.config.steam_shortcuts_path, |shortcuts| {
+        for shortcut in shortcuts.iter_mut().filter(|s| launched_instance(s).as_deref() == Some(inst.folder_name.as_str())) {
+            shortcut.last_play_time = shortcut.last_play_time.max(last_play_time);
+        }
+    })
+}
@@ -0,0 +1,38 @@
+//! Opal's library: everything but the entry point, so the integration tests in `tests/`
+//! can drive exports against a fake Steam and PrismLauncher.
+
+pub mod steam;
+pub mod ui;
+pub mod app;
+pub mod instances;
+pub mod export_page;
+pub mod settings_page;
+pub mod log_page;
+pub mod rules;
+pub mod cli;
+pub mod sync;
+pub mod doctor;
+pub mod doctor_page;
+pub mod migrate;
+pub mod collections;
+pub mod template;
+pub mod instance_options;
+pub mod artwork;
+pub mod merge;
+pub mod nbt;
+pub mod servers;
+pub mod worlds;
+pub mod accounts;
+pub mod controller;
+pub mod compat;
+pub mod playtime;
+pub mod launch;
+pub mod crash;
+pub mod hooks;
+pub mod backup;
+pub mod stats;
+pub mod stats_page;
+pub mod logger;
+pub mod bundle;
+pub mod history;
+pub mod platform;
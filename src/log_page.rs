@@ -1,14 +1,93 @@
-use std::path::PathBuf;
-use serde::{Serialize, Deserialize};
-use super::ui::TabPage;
+use eframe::egui::*;
+use log::{error, info, Level, LevelFilter};
+use super::ui::{TabPage, format_time_ago};
 use super::app::{AppModel, APP_HEADER_PADDING};
+use super::bundle::{bundle_file_name, create_diagnostic_bundle};
+use super::crash::Crash;
+use super::logger::{self, LogEntry};
+use super::rules::now_ms;
 
-/// The “Settings” tab, with a text input as an example of per-tab state.
-pub struct LogPage;
+const LEVELS : [LevelFilter; 5] = [LevelFilter::Error, LevelFilter::Warn, LevelFilter::Info, LevelFilter::Debug, LevelFilter::Trace];
+
+/// The “Logs” tab: crashes from wrapped launches, then everything Opal has logged.
+pub struct LogPage {
+    /// A copy of the logger's entries, taken when they change.
+    entries: Vec<LogEntry>,
+    generation: Option<u64>,
+    level: LevelFilter,
+    search: String,
+}
 
 impl Default for LogPage {
     fn default() -> Self {
-        Self
+        Self {
+            entries: Vec::new(),
+            generation: None,
+            level: LevelFilter::Info,
+            search: String::new(),
+        }
+    }
+}
+
+impl LogPage {
+    /// Returns whether the crash was dismissed.
+    fn crash_ui(ui: &mut Ui, crash: &Crash, now: u64) -> bool {
+        let mut dismissed = false;
+        Frame::group(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().error_fg_color, "✖");
+                ui.strong(format!("{} crashed", crash.name));
+                ui.label(format_time_ago(Some(crash.started), now));
+            });
+            ui.label(crash.exception.as_deref().unwrap_or("No exception was found in the log."));
+            if let Some(exit) = &crash.exit {
+                ui.label(format!("The game ended with {}.", exit));
+            }
+            if !crash.suspect_mods.is_empty() {
+                ui.label(format!("Suspected mods: {}", crash.suspect_mods.join(", ")));
+            }
+            ui.horizontal(|ui| {
+                ui.label(format!("Saved {} in {}", crash.files.join(" and "), crash.archive.display()));
+                if ui.small_button("Copy path").clicked() {
+                    ui.ctx().copy_text(crash.archive.display().to_string());
+                }
+                dismissed = ui.small_button("Dismiss").clicked();
+            });
+        });
+        dismissed
+    }
+
+    /// The entries that pass the level filter and the search, which matches message or module.
+    fn visible(&self) -> Vec<&LogEntry> {
+        let search = self.search.to_lowercase();
+        self.entries.iter()
+            .filter(|e| e.level <= self.level)
+            .filter(|e| search.is_empty() || e.message.to_lowercase().contains(&search) || e.module.to_lowercase().contains(&search))
+            .collect()
+    }
+
+    fn create_bundle(model: &AppModel) {
+        let Some(path) = rfd::FileDialog::new().set_file_name(bundle_file_name(now_ms())).add_filter("Zip", &["zip"]).save_file() else { return };
+        match create_diagnostic_bundle(model, &path) {
+            Ok(()) => info!("Saved a diagnostic bundle to {}", path.display()),
+            Err(e) => error!("Couldn't create the diagnostic bundle! {}", e),
+        }
+    }
+
+    fn save(lines: String) {
+        let Some(path) = rfd::FileDialog::new().set_file_name("opal.log").add_filter("Log", &["log", "txt"]).save_file() else { return };
+        if let Err(e) = std::fs::write(&path, lines) {
+            error!("Couldn't save the log! {}", e);
+        }
+    }
+}
+
+fn level_color(ui: &Ui, level: Level) -> Color32 {
+    match level {
+        Level::Error => ui.visuals().error_fg_color,
+        Level::Warn => ui.visuals().warn_fg_color,
+        Level::Info => ui.visuals().text_color(),
+        Level::Debug | Level::Trace => ui.visuals().weak_text_color(),
     }
 }
 
@@ -16,11 +95,80 @@ impl TabPage for LogPage {
     fn id(&self) -> &'static str { "log" }
     fn label(&self) -> &'static str { "Logs" }
 
-    fn ui(&mut self, ui: &mut eframe::egui::Ui, model: &mut AppModel) {
+    fn ui(&mut self, ui: &mut Ui, model: &mut AppModel) {
         ui.heading("Logs");
 
         ui.add_space(APP_HEADER_PADDING);
 
-        ui.label(&model.log_printout);
+        if !model.crashes.is_empty() {
+            let now = now_ms();
+            let mut dismissed = None;
+            for (i, crash) in model.crashes.iter().enumerate() {
+                if Self::crash_ui(ui, crash, now) { dismissed = Some(i); }
+            }
+            if let Some(i) = dismissed {
+                let mut crash = model.crashes.remove(i);
+                if let Err(e) = crash.dismiss() {
+                    error!("Couldn't dismiss the crash! {e}");
+                }
+            }
+            ui.separator();
+        }
+
+        let generation = logger::generation();
+        if self.generation != Some(generation) {
+            self.entries = logger::entries();
+            self.generation = Some(generation);
+        }
+
+        ui.horizontal(|ui| {
+            let name_label = ui.label("Show:");
+            ComboBox::from_id_salt("log_level")
+                .selected_text(self.level.as_str())
+                .show_ui(ui, |ui| {
+                    for level in LEVELS {
+                        ui.selectable_value(&mut self.level, level, level.as_str());
+                    }
+                })
+                .response
+                .labelled_by(name_label.id);
+            ui.add(TextEdit::singleline(&mut self.search).hint_text("Search").desired_width(200.0));
+            ui.separator();
+            let lines = || self.visible().iter().map(|e| e.format()).collect::<Vec<_>>().join("\n");
+            if ui.button("Copy").on_hover_text("Copy the entries shown").clicked() {
+                ui.ctx().copy_text(lines());
+            }
+            if ui.button("Save…").on_hover_text("Save the entries shown").clicked() {
+                Self::save(lines());
+            }
+            if ui.button("Clear").clicked() {
+                logger::clear();
+            }
+            ui.separator();
+            if ui.button("Create Diagnostic Bundle…")
+                .on_hover_text("A zip with the log, settings, instances and Opal's shortcuts for bug reports. Your user folder, Steam ID and account names are replaced.")
+                .clicked() {
+                Self::create_bundle(model);
+            }
+        });
+        if let Some(dir) = logger::log_dir() {
+            ui.weak(format!("Everything is also written to {}", dir.display()));
+        }
+
+        ui.separator();
+
+        let visible = self.visible();
+        let row_height = ui.text_style_height(&TextStyle::Body);
+        ScrollArea::vertical().auto_shrink([false, false]).stick_to_bottom(true)
+            .show_rows(ui, row_height, visible.len(), |ui, rows| {
+                for entry in &visible[rows] {
+                    ui.horizontal(|ui| {
+                        ui.weak(format_time_ago(Some(entry.time), now_ms())).on_hover_text(entry.format());
+                        ui.colored_label(level_color(ui, entry.level), entry.level.as_str());
+                        ui.weak(&entry.module);
+                        ui.add(Label::new(&entry.message).truncate()).on_hover_text(&entry.message);
+                    });
+                }
+            });
     }
-}
\ No newline at end of file
+}
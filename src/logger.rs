@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use super::rules::now_ms;
+use super::settings_page::data_dir;
+use super::ui::format_date_time;
+
+/// How many entries the Logs page can show. Older ones are only in the log files.
+const CAPACITY : usize = 2000;
+const LOGS_DIR : &str = "logs";
+const LOG_FILE_STEM : &str = "opal";
+/// When the log file is rotated.
+const MAX_FILE_SIZE : u64 = 1024 * 1024;
+/// `opal.log` plus `opal.1.log` to `opal.4.log`.
+const KEPT_FILES : usize = 5;
+/// Other crates only get logged from this level, so egui's chatter doesn't push out Opal's messages.
+const DEPENDENCY_LEVEL : LevelFilter = LevelFilter::Warn;
+
+static LOGGER : OnceLock<Logger> = OnceLock::new();
+
+/// One logged message.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// Milliseconds since the epoch.
+    pub time: u64,
+    pub level: Level,
+    /// The module that logged it, e.g. `opal::steam`.
+    pub module: String,
+    pub message: String,
+}
+impl LogEntry {
+    /// The entry as a line of the log file.
+    pub fn format(&self) -> String {
+        format!("{} {:<5} {}: {}", format_date_time(self.time), self.level, self.module, self.message)
+    }
+}
+
+struct LogFile {
+    file: File,
+    path: PathBuf,
+    size: u64,
+}
+impl LogFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { file, path, size })
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        if self.size + line.len() as u64 > MAX_FILE_SIZE {
+            rotate(&self.path)?;
+            *self = LogFile::open(self.path.clone())?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+}
+
+/// `opal.log` becomes `opal.1.log`, `opal.1.log` becomes `opal.2.log` and so on.
+fn rotate(path: &Path) -> io::Result<()> {
+    let numbered = |n: usize| path.with_file_name(format!("{}.{}.log", LOG_FILE_STEM, n));
+    let oldest = numbered(KEPT_FILES - 1);
+    if oldest.exists() { std::fs::remove_file(oldest)?; }
+    for n in (1..KEPT_FILES - 1).rev() {
+        if numbered(n).exists() { std::fs::rename(numbered(n), numbered(n + 1))?; }
+    }
+    std::fs::rename(path, numbered(1))
+}
+
+/// Keeps the latest entries for the Logs page, appends them to the log file and, like
+/// `env_logger` did, prints them to stderr according to `RUST_LOG`.
+struct Logger {
+    entries: Mutex<VecDeque<LogEntry>>,
+    file: Mutex<Option<LogFile>>,
+    /// Bumped on every change, so the Logs page knows when to copy the entries again.
+    generation: AtomicU64,
+    level: LevelFilter,
+    stderr_level: LevelFilter,
+}
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.stderr_level || metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        let entry = LogEntry {
+            time: now_ms(),
+            level: record.level(),
+            module: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        if entry.level <= self.stderr_level {
+            eprintln!("[{} {}] {}", entry.level, entry.module, entry.message);
+        }
+        if entry.level > self.level_for(&entry.module) { return; }
+
+        if let Ok(mut file) = self.file.lock() {
+            // There's nowhere left to report a failing log file, so it's just dropped.
+            if let Some(Err(_)) = file.as_mut().map(|f| f.write(&entry.format())) {
+                *file = None;
+            }
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() == CAPACITY { entries.pop_front(); }
+            entries.push_back(entry);
+        }
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(f) = file.as_mut() { let _ = f.file.flush(); }
+        }
+    }
+}
+impl Logger {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        if target == LOG_FILE_STEM || target.starts_with("opal::") { self.level } else { self.level.min(DEPENDENCY_LEVEL) }
+    }
+}
+
+/// Install the logger. `RUST_LOG` takes a single level (e.g. `debug`), which is logged
+/// everywhere; without it, the Logs page and files get `info` and stderr only errors.
+pub fn init() {
+    let env_level = std::env::var("RUST_LOG").ok().and_then(|level| level.parse::<LevelFilter>().ok());
+    let level = env_level.unwrap_or(LevelFilter::Info).max(LevelFilter::Info);
+    let stderr_level = env_level.unwrap_or(LevelFilter::Error);
+    let file = log_dir()
+        .and_then(|dir| std::fs::create_dir_all(&dir).ok().map(|_| dir.join(format!("{}.log", LOG_FILE_STEM))))
+        .and_then(|path| LogFile::open(path).ok());
+
+    let logger = LOGGER.get_or_init(|| Logger {
+        entries: Mutex::new(VecDeque::with_capacity(CAPACITY)),
+        file: Mutex::new(file),
+        generation: AtomicU64::new(0),
+        level,
+        stderr_level,
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(level.max(stderr_level));
+    }
+}
+
+/// Where the log files go: `<data dir>/logs`.
+pub fn log_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(LOGS_DIR))
+}
+
+/// A copy of the entries in memory, oldest first.
+pub fn entries() -> Vec<LogEntry> {
+    LOGGER.get()
+        .and_then(|logger| logger.entries.lock().ok().map(|entries| entries.iter().cloned().collect()))
+        .unwrap_or_default()
+}
+
+/// Changes whenever the entries do.
+pub fn generation() -> u64 {
+    LOGGER.get().map(|logger| logger.generation.load(Ordering::Relaxed)).unwrap_or(0)
+}
+
+/// Empty the Logs page. The log files keep everything.
+pub fn clear() {
+    let Some(logger) = LOGGER.get() else { return };
+    if let Ok(mut entries) = logger.entries.lock() { entries.clear(); }
+    logger.generation.fetch_add(1, Ordering::Relaxed);
+}
@@ -1,18 +1,16 @@
-mod steam;
-mod ui;
-mod app;
-mod instances;
-mod export_page;
-mod settings_page;
-mod log_page;
-
-use app::*;
+use opal::{app::*, cli, logger, ui};
 use eframe::egui::*;
 
 
 fn main() -> eframe::Result<()> {
 
-  env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+  logger::init(); // To the Logs page, the data folder and stderr (if you run with `RUST_LOG=debug`).
+
+  // Any argument means we're being used from the command line (or a script).
+  let args : Vec<String> = std::env::args().skip(1).collect();
+  if !args.is_empty() {
+    std::process::exit(cli::run(&args));
+  }
 
   let options = eframe::NativeOptions {
       viewport: ViewportBuilder {
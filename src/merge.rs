@@ -0,0 +1,290 @@
+use serde::{Serialize, Deserialize};
+use steam_shortcuts_util::shortcut::ShortcutOwned;
+use super::history::PlannedShortcut;
+use super::sync::LAUNCH_COMMAND;
+
+/// PrismLauncher arguments that pick what a shortcut launches, see `launch_target`.
+const TARGET_FLAGS : [&str; 8] = ["-l", "--launch", "-s", "--server", "-w", "--world", "-a", "--profile"];
+
+/// Shortcut fields that can be edited in Steam as well as in Opal.
+/// Opal always owns the exe, start folder, name and its own `-l <instance>`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShortcutField {
+    Hidden,
+    AllowOverlay,
+    AllowDesktopConfig,
+    OpenVr,
+    Icon,
+    ShortcutPath,
+    LastPlayTime,
+    Tags,
+    LaunchOptions,
+}
+impl ShortcutField {
+    pub const ALL : [ShortcutField; 9] = [
+        ShortcutField::Hidden, ShortcutField::AllowOverlay, ShortcutField::AllowDesktopConfig,
+        ShortcutField::OpenVr, ShortcutField::Icon, ShortcutField::ShortcutPath,
+        ShortcutField::LastPlayTime, ShortcutField::Tags, ShortcutField::LaunchOptions,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShortcutField::Hidden => "Hidden",
+            ShortcutField::AllowOverlay => "Steam overlay",
+            ShortcutField::AllowDesktopConfig => "Desktop controller config",
+            ShortcutField::OpenVr => "VR",
+            ShortcutField::Icon => "Icon",
+            ShortcutField::ShortcutPath => "Shortcut path",
+            ShortcutField::LastPlayTime => "Last play time",
+            ShortcutField::Tags => "Tags",
+            ShortcutField::LaunchOptions => "Extra launch options",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldPolicy {
+    /// Always write Opal's value.
+    Opal,
+    /// Keep what is in Steam, unless the instance sets the field in Opal. Tags and
+    /// launch options are merged: Steam's extras are kept next to Opal's.
+    Keep,
+}
+
+/// What to do with each field when re-exporting a shortcut that is already in Steam.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct MergePolicy {
+    pub hidden: FieldPolicy,
+    pub allow_overlay: FieldPolicy,
+    pub allow_desktop_config: FieldPolicy,
+    pub open_vr: FieldPolicy,
+    pub icon: FieldPolicy,
+    pub shortcut_path: FieldPolicy,
+    pub last_play_time: FieldPolicy,
+    pub tags: FieldPolicy,
+    pub launch_options: FieldPolicy,
+}
+impl Default for MergePolicy {
+    fn default() -> Self {
+        Self {
+            hidden: FieldPolicy::Keep,
+            allow_overlay: FieldPolicy::Keep,
+            allow_desktop_config: FieldPolicy::Keep,
+            open_vr: FieldPolicy::Keep,
+            icon: FieldPolicy::Keep,
+            shortcut_path: FieldPolicy::Keep,
+            last_play_time: FieldPolicy::Keep,
+            tags: FieldPolicy::Keep,
+            launch_options: FieldPolicy::Keep,
+        }
+    }
+}
+impl MergePolicy {
+    pub fn get_mut(&mut self, field: ShortcutField) -> &mut FieldPolicy {
+        match field {
+            ShortcutField::Hidden => &mut self.hidden,
+            ShortcutField::AllowOverlay => &mut self.allow_overlay,
+            ShortcutField::AllowDesktopConfig => &mut self.allow_desktop_config,
+            ShortcutField::OpenVr => &mut self.open_vr,
+            ShortcutField::Icon => &mut self.icon,
+            ShortcutField::ShortcutPath => &mut self.shortcut_path,
+            ShortcutField::LastPlayTime => &mut self.last_play_time,
+            ShortcutField::Tags => &mut self.tags,
+            ShortcutField::LaunchOptions => &mut self.launch_options,
+        }
+    }
+
+    /// Whether Steam's value should survive. `explicit` lists the fields the user set in Opal.
+    fn keeps(&self, field: ShortcutField, explicit: &[ShortcutField]) -> bool {
+        let policy = match field {
+            ShortcutField::Hidden => self.hidden,
+            ShortcutField::AllowOverlay => self.allow_overlay,
+            ShortcutField::AllowDesktopConfig => self.allow_desktop_config,
+            ShortcutField::OpenVr => self.open_vr,
+            ShortcutField::Icon => self.icon,
+            ShortcutField::ShortcutPath => self.shortcut_path,
+            ShortcutField::LastPlayTime => self.last_play_time,
+            ShortcutField::Tags => self.tags,
+            ShortcutField::LaunchOptions => self.launch_options,
+        };
+        policy == FieldPolicy::Keep && !explicit.contains(&field)
+    }
+
+    /// Combine the shortcut Opal wants with the one already in Steam. `previous` is what Opal
+    /// planned for this shortcut on the last export: its tags and launch options are Opal's
+    /// own, not the user's, and a field the instance set then still counts as set, so putting
+    /// it back to the default in Opal takes effect.
+    pub fn merge(&self, existing: &ShortcutOwned, mut new: ShortcutOwned, explicit: &[ShortcutField], previous: Option<&PlannedShortcut>) -> ShortcutOwned {
+        let was_explicit = previous.map(|p| p.explicit.as_slice()).unwrap_or_default();
+        let keeps = |field| self.keeps(field, explicit) && !was_explicit.contains(&field);
+
+        if keeps(ShortcutField::Hidden) { new.is_hidden = existing.is_hidden; }
+        if keeps(ShortcutField::AllowOverlay) { new.allow_overlay = existing.allow_overlay; }
+        if keeps(ShortcutField::AllowDesktopConfig) { new.allow_desktop_config = existing.allow_desktop_config; }
+        if keeps(ShortcutField::OpenVr) { new.open_vr = existing.open_vr; }
+        if keeps(ShortcutField::Icon) { new.icon = existing.icon.clone(); }
+        if keeps(ShortcutField::ShortcutPath) { new.shortcut_path = existing.shortcut_path.clone(); }
+        // Steam updates the last play time when it launches a shortcut, Opal when its launch
+        // wrapper sees the game exit. The later one wins.
+        if keeps(ShortcutField::LastPlayTime) { new.last_play_time = new.last_play_time.max(existing.last_play_time); }
+        // Tags and launch options are merged even when the instance sets some in Opal.
+        if self.tags == FieldPolicy::Keep {
+            let previous_tags = previous.map(|p| p.tags.as_slice()).unwrap_or_default();
+            for tag in existing.tags.iter() {
+                if !new.tags.contains(tag) && !previous_tags.contains(tag) { new.tags.push(tag.clone()); }
+            }
+        }
+        if self.launch_options == FieldPolicy::Keep {
+            let previous_options = previous.map(|p| p.launch_options.as_str()).unwrap_or_default();
+            new.launch_options = merge_launch_options(&existing.launch_options, &new.launch_options, previous_options);
+        }
+        new
+    }
+}
+
+/// Append the arguments the user added in Steam to Opal's launch options, as they were typed.
+/// Whole arguments are dropped, never single words of them: what the old options launch
+/// (`-l <instance>` and the server, world and account, plus the launch wrapper's command) is
+/// Opal's, as is anything Opal writes anyway or wrote on the last export (`previous`).
+pub fn merge_launch_options(existing: &str, opal: &str, previous: &str) -> String {
+    let mut ours = launch_arguments(opal);
+    ours.extend(launch_arguments(previous));
+    let mut theirs = launch_arguments(existing);
+    if theirs.first().is_some_and(|a| a.words == [LAUNCH_COMMAND]) {
+        theirs.remove(0);
+    }
+
+    let mut merged = opal.to_string();
+    for arg in theirs.into_iter() {
+        let targets = arg.words.first().is_some_and(|w| TARGET_FLAGS.contains(&w.as_str()));
+        if targets || ours.iter().any(|a| a.words == arg.words) { continue; }
+        merged.push(' ');
+        merged.push_str(&arg.raw);
+    }
+    merged
+}
+
+/// One launch argument: a flag with the value after it (`--server "a.example"`), or a single word.
+struct LaunchArgument {
+    /// As typed, quotes included.
+    raw: String,
+    /// Without quotes, like `split_launch_options` returns them.
+    words: Vec<String>,
+}
+
+fn launch_arguments(options: &str) -> Vec<LaunchArgument> {
+    // The words with where they start and end in `options`.
+    let mut words: Vec<(String, usize, usize)> = Vec::new();
+    let mut current: Option<(String, usize)> = None;
+    let mut quoted = false;
+    for (i, c) in options.char_indices() {
+        if c.is_whitespace() && !quoted {
+            if let Some((word, start)) = current.take() { words.push((word, start, i)); }
+            continue;
+        }
+        let (word, _) = current.get_or_insert_with(|| (String::new(), i));
+        if c == '"' { quoted = !quoted; } else { word.push(c); }
+    }
+    if let Some((word, start)) = current { words.push((word, start, options.len())); }
+
+    let mut args = Vec::new();
+    let mut words = words.into_iter().peekable();
+    while let Some((word, start, mut end)) = words.next() {
+        let mut arg_words = vec![word];
+        if arg_words[0].starts_with('-') {
+            if let Some((value, _, value_end)) = words.next_if(|(w, _, _)| !w.starts_with('-')) {
+                arg_words.push(value);
+                end = value_end;
+            }
+        }
+        args.push(LaunchArgument { raw: options[start..end].to_string(), words: arg_words });
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use steam_shortcuts_util::shortcut::Shortcut;
+    use super::*;
+
+    fn shortcut(launch_options: &str, tags: &[&str]) -> ShortcutOwned {
+        let mut shortcut = Shortcut::new("0", "All the Mods 9", "/prism/prismlauncher", "/prism", "", "", launch_options).to_owned();
+        shortcut.tags = tags.iter().map(|t| t.to_string()).collect();
+        shortcut
+    }
+
+    /// What Opal planned for `new` on the last export.
+    fn planned(new: &ShortcutOwned, explicit: &[ShortcutField]) -> PlannedShortcut {
+        PlannedShortcut {
+            app_id: new.app_id,
+            app_name: new.app_name.clone(),
+            exe: new.exe.clone(),
+            launch_options: new.launch_options.clone(),
+            tags: new.tags.clone(),
+            explicit: explicit.to_vec(),
+        }
+    }
+
+    #[test]
+    fn tags_opal_wrote_before_are_replaced() {
+        let before = shortcut("-l \"ATM9\"", &["1.20.1", "Modded"]);
+        let existing = shortcut("-l \"ATM9\"", &["1.20.1", "Modded", "Favourite"]);
+        let merged = MergePolicy::default().merge(&existing, shortcut("-l \"ATM9\"", &["1.21", "Modpacks"]), &[], Some(&planned(&before, &[])));
+        assert_eq!(merged.tags, ["1.21", "Modpacks", "Favourite"]);
+    }
+
+    #[test]
+    fn launch_arguments_opal_wrote_before_are_replaced() {
+        let policy = MergePolicy::default();
+        let server = shortcut("-l \"ATM9\" --server \"a.example\"", &[]);
+        let existing = shortcut("-l \"ATM9\" --server \"a.example\" -Dfoo=1", &[]);
+        let moved = policy.merge(&existing, shortcut("-l \"ATM9\" --server \"b.example\"", &[]), &[], Some(&planned(&server, &[])));
+        assert_eq!(moved.launch_options, "-l \"ATM9\" --server \"b.example\" -Dfoo=1");
+
+        let account = shortcut("-l \"ATM9\" -a \"Alex\"", &[]);
+        let merged = policy.merge(&account, shortcut("-l \"ATM9\"", &[]), &[], Some(&planned(&account, &[])));
+        assert_eq!(merged.launch_options, "-l \"ATM9\"");
+
+        let wrapped = shortcut("launch -l \"ATM9\"", &[]);
+        let merged = policy.merge(&wrapped, shortcut("-l \"ATM9\"", &[]), &[], Some(&planned(&wrapped, &[])));
+        assert_eq!(merged.launch_options, "-l \"ATM9\"");
+    }
+
+    #[test]
+    fn setting_a_field_back_to_the_default_counts() {
+        let policy = MergePolicy::default();
+        let mut hidden = shortcut("-l \"ATM9\"", &[]);
+        hidden.is_hidden = true;
+        let shown = policy.merge(&hidden, shortcut("-l \"ATM9\"", &[]), &[], Some(&planned(&hidden, &[ShortcutField::Hidden])));
+        assert!(!shown.is_hidden);
+
+        // Hidden in Steam, never set in Opal.
+        let kept = policy.merge(&hidden, shortcut("-l \"ATM9\"", &[]), &[], Some(&planned(&hidden, &[])));
+        assert!(kept.is_hidden);
+    }
+
+    #[test]
+    fn without_a_previous_export_steam_extras_are_kept() {
+        let existing = shortcut("-l \"ATM9\" --fullscreen", &["Favourite"]);
+        let merged = MergePolicy::default().merge(&existing, shortcut("-l \"ATM9\"", &["Modded"]), &[], None);
+        assert_eq!(merged.launch_options, "-l \"ATM9\" --fullscreen");
+        assert_eq!(merged.tags, ["Modded", "Favourite"]);
+    }
+
+    #[test]
+    fn flags_keep_their_values() {
+        let before = shortcut("-l \"ATM9\"", &[]);
+        let existing = shortcut("-l \"ATM9\" --foo ATM9 -Dtitle=\"A B\"", &[]);
+        let merged = MergePolicy::default().merge(&existing, shortcut("-l \"ATM9\"", &[]), &[], Some(&planned(&before, &[])));
+        assert_eq!(merged.launch_options, "-l \"ATM9\" --foo ATM9 -Dtitle=\"A B\"");
+    }
+
+    #[test]
+    fn without_a_previous_export_only_opal_arguments_are_dropped() {
+        let policy = MergePolicy::default();
+        let existing = shortcut("launch -l \"ATM9\" -a Alex --foo \"My Pack\" --demo", &[]);
+        let merged = policy.merge(&existing, shortcut("-l \"ATM9\" --demo", &[]), &[], None);
+        assert_eq!(merged.launch_options, "-l \"ATM9\" --demo --foo \"My Pack\"");
+    }
+}
@@ -0,0 +1,66 @@
+use std::io;
+use steam_shortcuts_util::{app_id_generator::calculate_app_id, shortcut::ShortcutOwned};
+use super::app::AppModel;
+use super::artwork::{grid_dir, move_grid_artwork};
+use super::steam::{edit_steam_shortcuts, read_steam_shortcuts};
+use super::sync::{is_wrapped, launched_instance};
+
+/// One Opal shortcut that points at a PrismLauncher install that isn't the current one.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub app_name: String,
+    pub old_exe: String,
+    pub old_app_id: u32,
+    /// Same as `old_app_id` unless app_ids are recomputed.
+    pub new_app_id: u32,
+}
+
+/// Find the Opal shortcuts whose exe or start folder isn't the configured PrismLauncher.
+/// Shortcuts that go through the launch wrapper only need the start folder to match.
+/// With `keep_app_ids` the shortcuts keep their app_id, so Steam keeps their artwork,
+/// play time and collections. Otherwise the app_id is recomputed from the new exe.
+pub fn plan_migration(shortcuts: &[ShortcutOwned], exe: &str, start_dir: &str, keep_app_ids: bool) -> Vec<Migration> {
+    shortcuts.iter()
+        .filter(|s| launched_instance(s).is_some())
+        .filter(|s| (s.exe != exe && !is_wrapped(s)) || s.start_dir != start_dir)
+        .map(|s| Migration {
+            app_name: s.app_name.clone(),
+            old_exe: s.exe.clone(),
+            old_app_id: s.app_id,
+            new_app_id: if keep_app_ids || is_wrapped(s) { s.app_id } else { calculate_app_id(exe, &s.app_name) },
+        })
+        .collect()
+}
+
+/// The migrations `migrate_shortcuts` would apply right now.
+pub fn pending_migrations(model: &AppModel, keep_app_ids: bool) -> io::Result<Vec<Migration>> {
+    let shortcuts = read_steam_shortcuts(model.files(), &model.config.steam_shortcuts_path)?;
+    let start_dir = model.config.prism_main_path.to_string_lossy().to_string();
+    Ok(plan_migration(&shortcuts, &model.prism_exe_path(), &start_dir, keep_app_ids))
+}
+
+/// Rewrite Opal shortcuts in place to point at the configured PrismLauncher, and move their
+/// grid artwork along if the app_id changes. The caller is responsible for closing Steam first.
+pub fn migrate_shortcuts(model: &AppModel, keep_app_ids: bool) -> io::Result<Vec<Migration>> {
+    let migrations = pending_migrations(model, keep_app_ids)?;
+    if migrations.is_empty() { return Ok(migrations); }
+
+    let exe = model.prism_exe_path();
+    let start_dir = model.config.prism_main_path.to_string_lossy().to_string();
+    edit_steam_shortcuts(model.files(), &model.config.steam_shortcuts_path, |shortcuts| {
+        for migration in migrations.iter() {
+            for shortcut in shortcuts.iter_mut().filter(|s| s.app_id == migration.old_app_id) {
+                if !is_wrapped(shortcut) { shortcut.exe = exe.clone(); }
+                shortcut.start_dir = start_dir.clone();
+                shortcut.app_id = migration.new_app_id;
+            }
+        }
+    })?;
+
+    if let Some(grid_dir) = grid_dir(&model.config.steam_shortcuts_path) {
+        for migration in migrations.iter().filter(|m| m.old_app_id != m.new_app_id) {
+            move_grid_artwork(model.files(), &grid_dir, migration.old_app_id, migration.new_app_id)?;
+        }
+    }
+    Ok(migrations)
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::path::Path;
+use flate2::read::GzDecoder;
+
+const GZIP_MAGIC : [u8; 2] = [0x1f, 0x8b];
+/// How deep lists and compounds may nest, Minecraft's own limit. Deeper files are rejected
+/// rather than overflowing the stack.
+const MAX_DEPTH : usize = 512;
+
+/// A value in Minecraft's NBT format, as used by `servers.dat` and `level.dat`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<u8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+impl Tag {
+    /// The child named `key`, if this is a compound that has one.
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Tag::Byte(n) => Some(*n as i64),
+            Tag::Short(n) => Some(*n as i64),
+            Tag::Int(n) => Some(*n as i64),
+            Tag::Long(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(list) => Some(list),
+            _ => None,
+        }
+    }
+}
+
+/// Read an NBT file, gzipped (like `level.dat`) or not (like `servers.dat`).
+pub fn read_file(path: &Path) -> io::Result<Tag> {
+    let bytes = std::fs::read(path)?;
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)?;
+        parse(&decompressed)
+    } else {
+        parse(&bytes)
+    }
+}
+
+/// Parse an uncompressed NBT file. The root is always a named compound, whose name is dropped.
+pub fn parse(bytes: &[u8]) -> io::Result<Tag> {
+    let mut reader = Reader { bytes, pos: 0, depth: 0 };
+    match reader.u8()? {
+        10 => {
+            reader.string()?;
+            reader.payload(10)
+        }
+        id => Err(invalid(format!("root tag is {} instead of a compound", id))),
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// NBT is big-endian throughout.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// How many lists and compounds the current tag is in.
+    depth: usize,
+}
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> io::Result<&[u8]> {
+        let end = self.pos.checked_add(n).filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| invalid("unexpected end of file"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("take returns N bytes"))
+    }
+
+    fn u8(&mut self) -> io::Result<u8> { Ok(self.array::<1>()?[0]) }
+    fn i16(&mut self) -> io::Result<i16> { Ok(i16::from_be_bytes(self.array()?)) }
+    fn i32(&mut self) -> io::Result<i32> { Ok(i32::from_be_bytes(self.array()?)) }
+    fn i64(&mut self) -> io::Result<i64> { Ok(i64::from_be_bytes(self.array()?)) }
+
+    fn len(&mut self) -> io::Result<usize> {
+        usize::try_from(self.i32()?).map_err(|_| invalid("negative length"))
+    }
+
+    /// Strings are Java's "modified UTF-8", which only differs from UTF-8 for NUL and
+    /// characters outside the BMP. Lossy decoding is plenty for names.
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.i16()? as u16 as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).to_string())
+    }
+
+    fn payload(&mut self, id: u8) -> io::Result<Tag> {
+        if id == 9 || id == 10 {
+            if self.depth == MAX_DEPTH { return Err(invalid("tags nested too deeply")); }
+            self.depth += 1;
+        }
+        let tag = self.value(id);
+        if id == 9 || id == 10 { self.depth -= 1; }
+        tag
+    }
+
+    fn value(&mut self, id: u8) -> io::Result<Tag> {
+        let tag = match id {
+            1 => Tag::Byte(self.u8()? as i8),
+            2 => Tag::Short(self.i16()?),
+            3 => Tag::Int(self.i32()?),
+            4 => Tag::Long(self.i64()?),
+            5 => Tag::Float(f32::from_be_bytes(self.array()?)),
+            6 => Tag::Double(f64::from_be_bytes(self.array()?)),
+            7 => {
+                let len = self.len()?;
+                Tag::ByteArray(self.take(len)?.to_vec())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let item_id = self.u8()?;
+                let len = self.len()?;
+                if item_id == 0 && len > 0 { return Err(invalid("list of end tags")); }
+                let mut list = Vec::new();
+                for _ in 0..len {
+                    list.push(self.payload(item_id)?);
+                }
+                Tag::List(list)
+            }
+            10 => {
+                let mut map = HashMap::new();
+                loop {
+                    let child_id = self.u8()?;
+                    if child_id == 0 { break; }
+                    let name = self.string()?;
+                    map.insert(name, self.payload(child_id)?);
+                }
+                Tag::Compound(map)
+            }
+            11 => {
+                let len = self.len()?;
+                let mut ints = Vec::new();
+                for _ in 0..len { ints.push(self.i32()?); }
+                Tag::IntArray(ints)
+            }
+            12 => {
+                let len = self.len()?;
+                let mut longs = Vec::new();
+                for _ in 0..len { longs.push(self.i64()?); }
+                Tag::LongArray(longs)
+            }
+            id => return Err(invalid(format!("unknown tag type {}", id))),
+        };
+        Ok(tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(bytes: &mut Vec<u8>, name: &str) {
+        bytes.extend((name.len() as u16).to_be_bytes());
+        bytes.extend(name.as_bytes());
+    }
+
+    /// `{ "": { name: "Our SMP", ip: "a.example", tags: [1, 2] } }`, like a `servers.dat` entry.
+    fn server() -> Vec<u8> {
+        let mut bytes = vec![10];
+        name(&mut bytes, "");
+        for (key, value) in [("name", "Our SMP"), ("ip", "a.example")] {
+            bytes.push(8);
+            name(&mut bytes, key);
+            name(&mut bytes, value);
+        }
+        bytes.push(9);
+        name(&mut bytes, "tags");
+        bytes.push(3);
+        bytes.extend(2i32.to_be_bytes());
+        bytes.extend(1i32.to_be_bytes());
+        bytes.extend(2i32.to_be_bytes());
+        bytes.push(0);
+        bytes
+    }
+
+    #[test]
+    fn compounds_and_lists() {
+        let root = parse(&server()).unwrap();
+        assert_eq!(root.get("name").and_then(Tag::as_str), Some("Our SMP"));
+        assert_eq!(root.get("ip").and_then(Tag::as_str), Some("a.example"));
+        assert_eq!(root.get("tags").and_then(Tag::as_list), Some([Tag::Int(1), Tag::Int(2)].as_slice()));
+    }
+
+    #[test]
+    fn truncated_files_are_invalid() {
+        let bytes = server();
+        for len in 0..bytes.len() {
+            assert_eq!(parse(&bytes[..len]).unwrap_err().kind(), io::ErrorKind::InvalidData, "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_invalid() {
+        // A list of lists of lists..., each with one item, far deeper than the limit.
+        let mut bytes = vec![10];
+        name(&mut bytes, "");
+        bytes.push(9);
+        name(&mut bytes, "deep");
+        for _ in 0..100_000 {
+            bytes.push(9);
+            bytes.extend(1i32.to_be_bytes());
+        }
+        assert_eq!(parse(&bytes).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut bytes = vec![10];
+        name(&mut bytes, "");
+        for _ in 0..MAX_DEPTH - 1 {
+            bytes.push(10);
+            name(&mut bytes, "a");
+        }
+        bytes.extend(vec![0; MAX_DEPTH]);
+        assert!(parse(&bytes).is_ok());
+    }
+}
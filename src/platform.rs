@@ -0,0 +1,87 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use steamlocate::SteamDir;
+use super::settings_page::data_dir;
+use super::steam::{ensure_steam_started, ensure_steam_stopped, is_steam_running, start_steam};
+
+/// Finds the Steam installation.
+pub trait SteamLocator {
+    fn steam_dir(&self) -> io::Result<PathBuf>;
+}
+
+/// Stops and starts Steam, which has to be closed while `shortcuts.vdf` is written.
+pub trait SteamProcess {
+    fn is_running(&self) -> bool;
+    /// Close Steam and wait for it to exit, checking every `timeout`.
+    fn stop(&self, timeout: Duration) -> io::Result<()>;
+    fn start(&self) -> io::Result<()>;
+    /// Wait until Steam shows up in the process list, or fail after `timeout`.
+    fn wait_until_started(&self, timeout: Duration) -> io::Result<()>;
+}
+
+/// Reads and writes Steam's files.
+pub trait FileIo {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    fn remove(&self, path: &Path) -> io::Result<()>;
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn exists(&self, path: &Path) -> bool;
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// The files and folders directly in `dir`.
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+}
+
+/// What Opal talks to when it edits Steam, and where it keeps its own data. The real system
+/// by default; tests swap in fakes.
+pub struct Platform {
+    pub locator: Box<dyn SteamLocator>,
+    pub steam: Box<dyn SteamProcess>,
+    pub files: Box<dyn FileIo>,
+    /// Opal's data folder, for the export history, play time, crashes and backups. `None`
+    /// if the user has none.
+    pub data_dir: Option<PathBuf>,
+}
+impl Default for Platform {
+    fn default() -> Self {
+        Self {
+            locator: Box::new(SteamDirLocator),
+            steam: Box::new(SystemSteam),
+            files: Box::new(DiskFiles),
+            data_dir: data_dir(),
+        }
+    }
+}
+
+/// Finds Steam with `steamlocate`.
+pub struct SteamDirLocator;
+impl SteamLocator for SteamDirLocator {
+    fn steam_dir(&self) -> io::Result<PathBuf> {
+        SteamDir::locate()
+            .map(|steam| steam.path().to_path_buf())
+            .map_err(|e| io::Error::new(io::ErrorKind::NotFound, e.to_string()))
+    }
+}
+
+/// The Steam processes on this machine.
+pub struct SystemSteam;
+impl SteamProcess for SystemSteam {
+    fn is_running(&self) -> bool { is_steam_running() }
+    fn stop(&self, timeout: Duration) -> io::Result<()> { ensure_steam_stopped(timeout) }
+    fn start(&self) -> io::Result<()> { start_steam() }
+    fn wait_until_started(&self, timeout: Duration) -> io::Result<()> { ensure_steam_started(timeout) }
+}
+
+/// The real filesystem.
+pub struct DiskFiles;
+impl FileIo for DiskFiles {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> { std::fs::read(path) }
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> { std::fs::write(path, contents) }
+    fn remove(&self, path: &Path) -> io::Result<()> { std::fs::remove_file(path) }
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> { std::fs::rename(from, to) }
+    fn exists(&self, path: &Path) -> bool { path.exists() }
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> { std::fs::create_dir_all(path) }
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        std::fs::read_dir(dir)?.map(|entry| entry.map(|e| e.path())).collect()
+    }
+}
@@ -0,0 +1,66 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+
+const PLAYTIME_FILE : &str = "playtime.json";
+
+/// One run of an instance through `opal launch`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Session {
+    /// Instance folder name.
+    pub instance: String,
+    /// Name and group at the time, so stats still make sense after a rename or removal.
+    pub name: String,
+    pub group: String,
+    /// When the game started, in milliseconds since the epoch.
+    pub started: u64,
+    pub duration_secs: u64,
+}
+impl Session {
+    pub fn ended(&self) -> u64 {
+        self.started + self.duration_secs * 1000
+    }
+}
+
+/// Every session Opal has seen, kept in Opal's data folder.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PlaytimeDb {
+    pub sessions: Vec<Session>,
+}
+impl PlaytimeDb {
+    pub fn file_path(data_dir: &Path) -> PathBuf {
+        data_dir.join(PLAYTIME_FILE)
+    }
+
+    /// Load the database, or an empty one if there is none yet. Unlike the config, a file
+    /// that can't be parsed is an error, so recording a session doesn't wipe the history.
+    pub fn load(data_dir: &Path) -> io::Result<Self> {
+        match std::fs::read_to_string(Self::file_path(data_dir)) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, data_dir: &Path) -> io::Result<()> {
+        let path = Self::file_path(data_dir);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    /// Add a session and save right away.
+    pub fn record(data_dir: &Path, session: Session) -> io::Result<()> {
+        let mut db = Self::load(data_dir)?;
+        db.sessions.push(session);
+        db.save(data_dir)
+    }
+
+    /// When the instance was last played through Opal, in milliseconds since the epoch.
+    pub fn last_played(&self, folder: &str) -> Option<u64> {
+        self.sessions.iter().filter(|s| s.instance == folder).map(Session::ended).max()
+    }
+}
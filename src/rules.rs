@@ -0,0 +1,192 @@
+use std::cmp::Ordering;
+use std::time::{SystemTime, UNIX_EPOCH};
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use super::instances::{Instance, ModLoader};
+
+const MS_PER_DAY : u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleAction {
+    Include,
+    Exclude,
+}
+
+/// What a rule looks at on an `Instance`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum RuleCondition {
+    Everything,
+    Group(String),
+    NameGlob(String),
+    NameRegex(String),
+    Loader(ModLoader),
+    /// Inclusive on both ends. An empty bound is open.
+    VersionRange { min: String, max: String },
+    LaunchedWithinDays(u32),
+}
+impl RuleCondition {
+    /// One default value per variant, in the order the rule editor lists them.
+    pub fn kinds() -> Vec<RuleCondition> {
+        vec![
+            RuleCondition::Everything,
+            RuleCondition::Group(String::new()),
+            RuleCondition::NameGlob(String::from("*")),
+            RuleCondition::NameRegex(String::from(".*")),
+            RuleCondition::Loader(ModLoader::Fabric),
+            RuleCondition::VersionRange { min: String::new(), max: String::new() },
+            RuleCondition::LaunchedWithinDays(30),
+        ]
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            RuleCondition::Everything => "Everything",
+            RuleCondition::Group(_) => "Group is",
+            RuleCondition::NameGlob(_) => "Name matches glob",
+            RuleCondition::NameRegex(_) => "Name matches regex",
+            RuleCondition::Loader(_) => "Mod loader is",
+            RuleCondition::VersionRange { .. } => "Minecraft version in",
+            RuleCondition::LaunchedWithinDays(_) => "Launched in the last days",
+        }
+    }
+
+    /// The compiled name pattern, `Ok(None)` for conditions without one.
+    fn compile(&self) -> Result<Option<Regex>, String> {
+        match self {
+            RuleCondition::NameGlob(glob) => Regex::new(&glob_to_regex(glob)).map(Some).map_err(|e| e.to_string()),
+            RuleCondition::NameRegex(re) => Regex::new(re).map(Some).map_err(|e| e.to_string()),
+            _ => Ok(None),
+        }
+    }
+
+    /// `pattern` is what `compile` made of this condition. `now_ms` is passed in so a whole
+    /// rule set is evaluated against the same instant.
+    fn matches(&self, pattern: Option<&Regex>, inst: &Instance, now_ms: u64) -> bool {
+        match self {
+            RuleCondition::Everything => true,
+            RuleCondition::Group(group) => inst.group.eq_ignore_ascii_case(group),
+            RuleCondition::NameGlob(_) | RuleCondition::NameRegex(_) => pattern
+                .is_some_and(|re| re.is_match(&inst.folder_name) || re.is_match(inst.display_name())),
+            RuleCondition::Loader(loader) => inst.loader == *loader,
+            RuleCondition::VersionRange { min, max } => {
+                let Some(version) = &inst.mc_version else { return false };
+                (min.trim().is_empty() || compare_versions(version, min) != Ordering::Less)
+                    && (max.trim().is_empty() || compare_versions(version, max) != Ordering::Greater)
+            }
+            RuleCondition::LaunchedWithinDays(days) => match inst.last_launch {
+                Some(last) => now_ms.saturating_sub(last) <= *days as u64 * MS_PER_DAY,
+                None => false,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SelectionRule {
+    pub enabled: bool,
+    pub action: RuleAction,
+    pub condition: RuleCondition,
+}
+impl Default for SelectionRule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            action: RuleAction::Include,
+            condition: RuleCondition::Everything,
+        }
+    }
+}
+
+/// Ordered list of rules. Like a `.gitignore`, the last enabled rule that matches
+/// an instance decides whether it is selected; instances no rule matches are left out.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RuleSet {
+    pub rules: Vec<SelectionRule>,
+}
+impl RuleSet {
+    pub fn is_empty(&self) -> bool {
+        !self.rules.iter().any(|r| r.enabled)
+    }
+
+    /// Compile the name patterns, once per change of the rules rather than once per instance.
+    pub fn compile(&self) -> CompiledRules {
+        CompiledRules {
+            patterns: self.rules.iter().map(|r| r.condition.compile()).collect(),
+            rules: self.clone(),
+        }
+    }
+
+    /// Overwrite the `checked` state of every instance with the rule outcome.
+    pub fn apply(&self, instances: &mut [Instance]) {
+        let compiled = self.compile();
+        let now = now_ms();
+        for inst in instances.iter_mut() {
+            inst.checked = compiled.matches(inst, now);
+        }
+    }
+}
+
+/// A `RuleSet` with its name patterns compiled, one entry per rule.
+#[derive(Debug, Clone)]
+pub struct CompiledRules {
+    rules: RuleSet,
+    patterns: Vec<Result<Option<Regex>, String>>,
+}
+impl CompiledRules {
+    /// Whether this was compiled from `rules`, so callers can keep it until the rules change.
+    pub fn is_for(&self, rules: &RuleSet) -> bool {
+        self.rules == *rules
+    }
+
+    pub fn matches(&self, inst: &Instance, now_ms: u64) -> bool {
+        self.decision(inst, now_ms) == Some(RuleAction::Include)
+    }
+
+    /// The action of the rule that decides about `inst`, `None` if no rule matches it.
+    pub fn decision(&self, inst: &Instance, now_ms: u64) -> Option<RuleAction> {
+        self.rules.rules.iter().zip(self.patterns.iter())
+            .rev()
+            .filter(|(r, _)| r.enabled)
+            .find(|(r, pattern)| pattern.as_ref().is_ok_and(|p| r.condition.matches(p.as_ref(), inst, now_ms)))
+            .map(|(r, _)| r.action)
+    }
+
+    /// Why the pattern of rule `i` doesn't compile, so the editor can point at broken rules.
+    pub fn error(&self, i: usize) -> Option<&str> {
+        self.patterns.get(i)?.as_ref().err().map(String::as_str)
+    }
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// Translate a shell-style glob (`*`, `?`) into an anchored, case-insensitive regex.
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from("(?i)^");
+    for c in glob.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+/// Compare dotted version strings numerically ("1.9" < "1.20.1").
+/// Non-numeric parts (snapshots, pre-releases) compare as 0.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parse = |v: &str| -> Vec<u32> {
+        v.trim().split(['.', '-', '_'])
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    let (a, b) = (parse(a), parse(b));
+    for i in 0..a.len().max(b.len()) {
+        let ord = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ord != Ordering::Equal { return ord; }
+    }
+    Ordering::Equal
+}
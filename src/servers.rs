@@ -0,0 +1,44 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use super::nbt;
+
+/// Minecraft's game folder inside an instance; older instances use `.minecraft`.
+pub const GAME_DIRS : [&str; 2] = [".minecraft", "minecraft"];
+const SERVERS_FILE : &str = "servers.dat";
+
+/// A server that gets its own quick-join shortcut (`--server <address>`).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Server {
+    /// Shown after the instance name, e.g. "ATM9 — Our SMP".
+    pub name: String,
+    /// `host` or `host:port`.
+    pub address: String,
+    /// Icon for this shortcut instead of the instance's.
+    pub icon: Option<PathBuf>,
+}
+
+/// The game folder of the instance at `instance_path`, if it has been launched before.
+pub fn game_dir(instance_path: &Path) -> Option<PathBuf> {
+    GAME_DIRS.iter()
+        .map(|dir| instance_path.join(dir))
+        .find(|dir| dir.is_dir())
+}
+
+/// The multiplayer server list of an instance. Instances that have no list yet have no servers.
+pub fn read_server_list(instance_path: &Path) -> io::Result<Vec<Server>> {
+    let Some(path) = game_dir(instance_path).map(|dir| dir.join(SERVERS_FILE)).filter(|p| p.is_file()) else {
+        return Ok(Vec::new());
+    };
+    let root = nbt::read_file(&path)?;
+
+    Ok(root.get("servers").and_then(|s| s.as_list()).unwrap_or_default().iter()
+        .filter_map(|entry| {
+            let address = entry.get("ip")?.as_str()?.trim().to_string();
+            if address.is_empty() { return None; }
+            let name = entry.get("name").and_then(|n| n.as_str()).unwrap_or(&address).to_string();
+            Some(Server { name, address, icon: None })
+        })
+        .collect())
+}
@@ -1,18 +1,46 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, io, path::PathBuf};
 use serde::{Serialize, Deserialize};
 use log::{error};
+use directories::ProjectDirs;
+use eframe::egui::{CollapsingHeader, ComboBox, DragValue, Grid, TextEdit};
 use super::ui::TabPage;
-use super::app::{AppModel, APP_HEADER_PADDING};
-use super::instances::default_prism_path;
-use super::steam::default_steam_shortcuts_path;
+use super::app::{AppModel, APP_NAME, APP_HEADER_PADDING};
+use super::backup::BackupSettings;
+use super::collections::CollectionSettings;
+use super::compat::CompatTool;
+use super::controller::ControllerSettings;
+use super::hooks::{HookFailure, Hooks};
+use super::template::{render, NamingSettings, PLACEHOLDERS};
+use super::instance_options::InstanceOptions;
+use super::launch::{AlreadyRunning, LaunchSettings};
+use super::merge::{FieldPolicy, MergePolicy, ShortcutField};
+use super::instances::{default_prism_path, Instance};
+use super::migrate::{migrate_shortcuts, pending_migrations, Migration};
+use super::rules::RuleSet;
+use super::platform::{FileIo, SteamLocator};
+use super::steam::{default_steam_shortcuts_path, default_steam_user, ShortcutPlacement};
+
+const CONFIG_FILE_NAME : &str = "config.json";
 
 // ---------- Settings model ----------
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct Config {
     pub prism_main_path: PathBuf,
     pub steam_shortcuts_path: PathBuf,
     pub user_id : u32,
     pub include_hidden: bool,
+    pub selection_rules: RuleSet,
+    pub collections: CollectionSettings,
+    pub naming: NamingSettings,
+    /// Keyed by instance folder name. Instances without an entry use the defaults.
+    pub instance_options: HashMap<String, InstanceOptions>,
+    pub merge_policy: MergePolicy,
+    pub shortcut_placement: ShortcutPlacement,
+    pub controller: ControllerSettings,
+    /// Steam Play tool for every shortcut, unless the instance sets its own.
+    pub compat_tool: CompatTool,
+    pub launch: LaunchSettings,
 }
 impl Default for Config {
     fn default() -> Self {
@@ -20,25 +48,142 @@ impl Default for Config {
             prism_main_path: 
                 match default_prism_path() {
                     Ok(p) => p,
-                    Err(e) => PathBuf::new()
+                    Err(_) => PathBuf::new()
                 },
             include_hidden: false,
-            user_id: 86176907,
-            steam_shortcuts_path :
-                match default_steam_shortcuts_path(86176907) {
-                    Ok(p) => p,
-                    Err(e) => PathBuf::new()
-                },
+            // Found in Steam by `Config::load`.
+            user_id: 0,
+            steam_shortcuts_path: PathBuf::new(),
+            selection_rules: RuleSet::default(),
+            collections: CollectionSettings::default(),
+            naming: NamingSettings::default(),
+            instance_options: HashMap::new(),
+            merge_policy: MergePolicy::default(),
+            shortcut_placement: ShortcutPlacement::default(),
+            controller: ControllerSettings::default(),
+            compat_tool: CompatTool::default(),
+            launch: LaunchSettings::default(),
         }
     }
 }
+impl Config {
+    /// Where Opal keeps its settings (e.g. `%APPDATA%\Opal\config\config.json`).
+    pub fn file_path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", APP_NAME).map(|dirs| dirs.config_dir().join(CONFIG_FILE_NAME))
+    }
+
+    /// Load the saved config, or the defaults if there is none yet (or it can't be read).
+    /// Without a shortcuts file set, the one of the last Steam user is found with `locator`.
+    pub fn load(locator: &dyn SteamLocator, files: &dyn FileIo) -> Self {
+        let mut config = Self::load_saved();
+        if config.steam_shortcuts_path.as_os_str().is_empty() {
+            config.find_steam_user(locator, files);
+        }
+        config
+    }
+
+    fn load_saved() -> Self {
+        let Some(path) = Self::file_path() else { return Self::default() };
+        match std::fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str(&json) {
+                Ok(config) => config,
+                Err(e) => {
+                    error!("Couldn't parse config at {}! {}", path.display(), e);
+                    Self::default()
+                }
+            },
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Use the shortcuts of the user who logged in to Steam last.
+    pub fn find_steam_user(&mut self, locator: &dyn SteamLocator, files: &dyn FileIo) {
+        let found = default_steam_user(locator, files)
+            .and_then(|id| Ok((id, default_steam_shortcuts_path(locator, id)?)));
+        match found {
+            Ok((id, path)) => {
+                self.user_id = id;
+                self.steam_shortcuts_path = path;
+            }
+            Err(e) => error!("Couldn't find the Steam user! {}", e),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::file_path() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "No config directory for this user"));
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(path, json)
+    }
+}
+
+/// Where Opal keeps everything besides its settings, like the play time database.
+pub fn data_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "", APP_NAME).map(|dirs| dirs.data_dir().to_path_buf())
+}
 
 /// The “Settings” tab, with a text input as an example of per-tab state.
-pub struct SettingsPage;
+pub struct SettingsPage {
+    /// Result of the last check for shortcuts pointing at another PrismLauncher install.
+    pending_migrations: Option<Vec<Migration>>,
+    keep_app_ids: bool,
+}
 
 impl Default for SettingsPage {
     fn default() -> Self {
-        Self
+        Self {
+            pending_migrations: None,
+            keep_app_ids: true,
+        }
+    }
+}
+
+impl SettingsPage {
+    fn check_migrations(&mut self, model: &mut AppModel) {
+        match pending_migrations(model, self.keep_app_ids) {
+            Ok(pending) => self.pending_migrations = Some(pending),
+            Err(e) => {
+                error!("Couldn't read shortcuts! {}", e);
+                self.pending_migrations = None;
+            }
+        }
+    }
+
+    /// Point existing Opal shortcuts at the configured PrismLauncher after it moved.
+    fn repair_ui(&mut self, ui: &mut eframe::egui::Ui, model: &mut AppModel) {
+        ui.strong("Repair Shortcuts");
+        ui.label("If PrismLauncher was moved or reinstalled, point the existing shortcuts at the folder above.");
+
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut self.keep_app_ids, "Keep app IDs").changed() {
+                self.pending_migrations = None;
+            }
+            ui.weak("(keeps artwork, play time and collections)");
+        });
+
+        if ui.button("Check Shortcuts").clicked() {
+            self.check_migrations(model);
+        }
+
+        let Some(pending) = &self.pending_migrations else { return };
+        if pending.is_empty() {
+            ui.label("All shortcuts already point to this PrismLauncher.");
+            return;
+        }
+        for migration in pending.iter() {
+            ui.label(format!("{}: {}", migration.app_name, migration.old_exe));
+        }
+        if ui.button(format!("Repair {} Shortcuts", pending.len())).clicked() {
+            let keep_app_ids = self.keep_app_ids;
+            if let Err(e) = model.while_steam_closed(|model| migrate_shortcuts(model, keep_app_ids)) {
+                error!("Couldn't repair shortcuts! {}", e);
+            }
+            self.check_migrations(model);
+        }
     }
 }
 
@@ -59,10 +204,13 @@ impl TabPage for SettingsPage {
                 if let Some(folder) = rfd::FileDialog::new().set_directory(".").pick_folder() {
                     model.config.prism_main_path = folder;
                     model.update_instances();
+                    model.keep_exported_selected();
+                    self.check_migrations(model);
                 }
             }
             if ui.button("🔄").clicked() {
                 model.update_instances();
+                model.keep_exported_selected();
             };
         });
         
@@ -71,15 +219,7 @@ impl TabPage for SettingsPage {
             ui.text_edit_singleline(&mut model.config.user_id.to_string())
                 .labelled_by(name_label.id);
             if ui.button("🔄").clicked() {
-                match default_steam_shortcuts_path(model.config.user_id) {
-                    Ok(path) => model.config.steam_shortcuts_path = path,
-                    Err(e) => {
-                        let msg = format!("\nCouldn't find shortcuts file path! {}", e);
-                        error!("{}", &msg);
-                        model.log_printout.push_str(&msg);
-                        return;
-                    }
-                }
+                model.config.find_steam_user(model.platform.locator.as_ref(), model.platform.files.as_ref());
             };
         });
 
@@ -99,5 +239,246 @@ impl TabPage for SettingsPage {
             ui.checkbox(&mut model.config.include_hidden, "")
                 .labelled_by(name_label.id);
         });
+
+        ui.separator();
+
+        naming_ui(ui, model);
+
+        ui.separator();
+
+        collections_ui(ui, &mut model.config.collections);
+
+        ui.separator();
+
+        let mut groups: Vec<String> = model.instances.iter().map(|i| i.group.clone()).filter(|g| !g.is_empty()).collect();
+        groups.sort();
+        groups.dedup();
+        launch_ui(ui, &mut model.config.launch, &groups);
+
+        ui.separator();
+
+        controller_ui(ui, &mut model.config.controller);
+
+        ui.separator();
+
+        if cfg!(target_os = "linux") {
+            ui.strong("Steam Play");
+            ui.horizontal(|ui| {
+                let name_label = ui.label("Compatibility Tool:");
+                ComboBox::from_id_salt("compat_tool")
+                    .selected_text(model.config.compat_tool.label())
+                    .show_ui(ui, |ui| compat_tool_menu(ui, &mut model.config.compat_tool, &model.compat_tools))
+                    .response
+                    .labelled_by(name_label.id);
+            });
+            ui.weak("\"No compatibility tool\" stops Steam from running PrismLauncher through Proton.");
+            ui.separator();
+        }
+
+        ui.horizontal(|ui| {
+            let name_label = ui.label("New Shortcuts Go:");
+            ComboBox::from_id_salt("shortcut_placement")
+                .selected_text(model.config.shortcut_placement.label())
+                .show_ui(ui, |ui| {
+                    for placement in ShortcutPlacement::ALL {
+                        ui.selectable_value(&mut model.config.shortcut_placement, placement, placement.label());
+                    }
+                })
+                .response
+                .labelled_by(name_label.id);
+        });
+
+        merge_policy_ui(ui, &mut model.config.merge_policy);
+
+        ui.separator();
+
+        self.repair_ui(ui, model);
+    }
+}
+fn launch_ui(ui: &mut eframe::egui::Ui, settings: &mut LaunchSettings, groups: &[String]) {
+    ui.strong("Play Time");
+    ui.checkbox(&mut settings.use_wrapper, "Launch through Opal to record play time")
+        .on_hover_text("Shortcuts run `opal launch`, which starts PrismLauncher and waits for the game to exit.");
+    ui.add_enabled_ui(settings.use_wrapper, |ui| {
+        ui.checkbox(&mut settings.sync_prism_play_time, "Add sessions to the play time PrismLauncher shows");
+        ui.weak("PrismLauncher counts play time itself while it stays open, so only turn this on if its totals are missing sessions.");
+        ui.horizontal(|ui| {
+            let name_label = ui.label("Wait for the game to start:");
+            ui.add(DragValue::new(&mut settings.start_timeout_secs).range(10..=3600).suffix(" s"))
+                .labelled_by(name_label.id);
+        });
+        ui.horizontal(|ui| {
+            let name_label = ui.label("When the game is already running:");
+            ComboBox::from_id_salt("already_running")
+                .selected_text(settings.already_running.label())
+                .show_ui(ui, |ui| {
+                    for option in AlreadyRunning::ALL {
+                        ui.selectable_value(&mut settings.already_running, option, option.label());
+                    }
+                })
+                .response
+                .labelled_by(name_label.id);
+        });
+        ui.checkbox(&mut settings.backup.enabled, "Back up worlds before each launch");
+        ui.add_enabled_ui(settings.backup.enabled, |ui| backup_keep_ui(ui, &mut settings.backup));
+        CollapsingHeader::new("Group Hooks").show(ui, |ui| {
+            ui.weak("Commands run around launches of a group's instances. Instances with hooks of their own use those instead.");
+            for group in groups {
+                CollapsingHeader::new(group).id_salt(("group_hooks", group)).show(ui, |ui| {
+                    let mut hooks = settings.group_hooks.get(group).cloned().unwrap_or_default();
+                    hooks_ui(ui, group, &mut hooks);
+                    if hooks.is_empty() { settings.group_hooks.remove(group); }
+                    else { settings.group_hooks.insert(group.clone(), hooks); }
+                });
+            }
+        });
+    });
+}
+
+pub fn backup_keep_ui(ui: &mut eframe::egui::Ui, settings: &mut BackupSettings) {
+    ui.horizontal(|ui| {
+        let name_label = ui.label("Backups to keep per instance:");
+        ui.add(DragValue::new(&mut settings.keep).range(1..=100))
+            .labelled_by(name_label.id);
+    });
+}
+
+/// Editing pre-launch and post-exit commands. `id_salt` tells the drop-downs apart.
+pub fn hooks_ui(ui: &mut eframe::egui::Ui, id_salt: &str, hooks: &mut Hooks) {
+    Grid::new(("hooks", id_salt)).num_columns(2).show(ui, |ui| {
+        let name_label = ui.label("Before launch:");
+        ui.add(TextEdit::singleline(&mut hooks.pre_launch).hint_text("e.g. ./sync.sh pull"))
+            .labelled_by(name_label.id);
+        ui.end_row();
+        let name_label = ui.label("After exit:");
+        ui.add(TextEdit::singleline(&mut hooks.post_exit).hint_text("e.g. ./sync.sh push"))
+            .labelled_by(name_label.id);
+        ui.end_row();
+        let name_label = ui.label("Timeout:");
+        ui.add(DragValue::new(&mut hooks.timeout_secs).range(1..=3600).suffix(" s"))
+            .labelled_by(name_label.id);
+        ui.end_row();
+        let name_label = ui.label("If a hook fails:");
+        ComboBox::from_id_salt(("hook_failure", id_salt))
+            .selected_text(hooks.on_failure.label())
+            .show_ui(ui, |ui| {
+                for policy in HookFailure::ALL {
+                    ui.selectable_value(&mut hooks.on_failure, policy, policy.label());
+                }
+            })
+            .response
+            .labelled_by(name_label.id);
+        ui.end_row();
+    });
+    ui.weak("Run in the instance folder, with OPAL_INSTANCE, OPAL_INSTANCE_PATH, OPAL_GAME_DIR, OPAL_GROUP, \
+        OPAL_MC_VERSION and, after exit, OPAL_SESSION_SECS set.");
+}
+
+/// The entries of a compatibility tool drop-down.
+pub fn compat_tool_menu(ui: &mut eframe::egui::Ui, value: &mut CompatTool, tools: &[String]) {
+    ui.selectable_value(value, CompatTool::Unchanged, CompatTool::Unchanged.label());
+    ui.selectable_value(value, CompatTool::NoTool, CompatTool::NoTool.label());
+    for tool in tools {
+        ui.selectable_value(value, CompatTool::Tool(tool.clone()), tool);
+    }
+}
+
+fn controller_ui(ui: &mut eframe::egui::Ui, settings: &mut ControllerSettings) {
+    ui.strong("Steam Input");
+    ui.checkbox(&mut settings.enabled, "Give exported shortcuts a Minecraft controller layout");
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        ui.weak("Instances with Controlify or MidnightControls get a gamepad layout for the mod, others keyboard and mouse.");
+        ui.checkbox(&mut settings.overwrite, "Replace layouts that were changed in Steam");
+    });
+}
+
+fn collections_ui(ui: &mut eframe::egui::Ui, settings: &mut CollectionSettings) {
+    ui.strong("Steam Collections");
+    ui.checkbox(&mut settings.enabled, "Add each PrismLauncher group to a Steam collection of the same name");
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        ui.horizontal(|ui| {
+            let name_label = ui.label("Collection Name Prefix:");
+            ui.text_edit_singleline(&mut settings.prefix)
+                .labelled_by(name_label.id);
+        });
+        ui.checkbox(&mut settings.skip_ungrouped, "Leave instances without a group out of collections");
+    });
+}
+
+/// Name and tag templates, with a live preview and per-instance name overrides.
+fn naming_ui(ui: &mut eframe::egui::Ui, model: &mut AppModel) {
+    ui.strong("Shortcut Names and Tags");
+    ui.label(PLACEHOLDERS.iter().map(|(key, _)| format!("{{{}}}", key)).collect::<Vec<_>>().join("  "))
+        .on_hover_text(PLACEHOLDERS.iter().map(|(key, what)| format!("{{{}}}: {}", key, what)).collect::<Vec<_>>().join("\n"));
+
+    let naming = &mut model.config.naming;
+    let example = model.instances.first();
+    ui.horizontal(|ui| {
+        let name_label = ui.label("Name Template:");
+        ui.text_edit_singleline(&mut naming.name_template)
+            .labelled_by(name_label.id);
+        template_status(ui, &naming.name_template, example);
+    });
+
+    let mut remove = None;
+    for (i, tag) in naming.tag_templates.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("Tag {}:", i + 1));
+            ui.text_edit_singleline(tag);
+            template_status(ui, tag, example);
+            if ui.small_button("🗑").clicked() { remove = Some(i); }
+        });
     }
-}
\ No newline at end of file
+    if let Some(i) = remove { naming.tag_templates.remove(i); }
+    if ui.button("Add Tag").clicked() {
+        naming.tag_templates.push(String::from("{group}"));
+    }
+
+    CollapsingHeader::new("Preview and Name Overrides").id_salt("name_overrides").show(ui, |ui| {
+        ui.weak("Renaming keeps the app ID of shortcuts that are already in Steam, so their artwork stays.");
+        Grid::new("name_overrides_grid").num_columns(3).striped(true).show(ui, |ui| {
+            ui.strong("Instance");
+            ui.strong("Override");
+            ui.strong("Shortcut Name and Tags");
+            ui.end_row();
+            for inst in model.instances.iter() {
+                ui.label(&inst.folder_name);
+                let mut name = naming.name_overrides.get(&inst.folder_name).cloned().unwrap_or_default();
+                let hint = render(&naming.name_template, inst).unwrap_or_default();
+                if ui.add(TextEdit::singleline(&mut name).hint_text(hint)).changed() {
+                    if name.is_empty() { naming.name_overrides.remove(&inst.folder_name); }
+                    else { naming.name_overrides.insert(inst.folder_name.clone(), name); }
+                }
+                ui.label(format!("{}  [{}]", naming.app_name(inst), naming.tags(inst).join(", ")));
+                ui.end_row();
+            }
+        });
+    });
+}
+
+/// Show what a template renders to for `example`, or why it can't be rendered.
+fn template_status(ui: &mut eframe::egui::Ui, template: &str, example: Option<&Instance>) {
+    let Some(example) = example else { return };
+    match render(template, example) {
+        Ok(text) => { ui.weak(format!("→ {}", text)); }
+        Err(e) => { ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", e)); }
+    }
+}
+
+fn merge_policy_ui(ui: &mut eframe::egui::Ui, policy: &mut MergePolicy) {
+    CollapsingHeader::new("Re-exporting Existing Shortcuts").id_salt("merge_policy").show(ui, |ui| {
+        ui.label("Which changes made in Steam survive when a shortcut is exported again. \
+            \"Keep Steam's\" still applies options you set on an instance in Opal; tags and launch options are merged.");
+        Grid::new("merge_policy_grid").num_columns(2).show(ui, |ui| {
+            for field in ShortcutField::ALL {
+                ui.label(field.label());
+                let value = policy.get_mut(field);
+                ui.horizontal(|ui| {
+                    ui.selectable_value(value, FieldPolicy::Keep, "Keep Steam's");
+                    ui.selectable_value(value, FieldPolicy::Opal, "Use Opal's");
+                });
+                ui.end_row();
+            }
+        });
+    });
+}
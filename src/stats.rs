@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use serde::Serialize;
+use super::instances::Instance;
+use super::playtime::Session;
+
+const WEEK_MS : u64 = 7 * 24 * 60 * 60 * 1000;
+const CSV_HEADER : &str = "instance,name,group,total_secs,opal_secs,sessions,longest_session_secs,last_played_ms";
+
+/// Play time of one instance, from PrismLauncher's `instance.cfg` and Opal's sessions.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PackStats {
+    /// Instance folder name.
+    pub instance: String,
+    pub name: String,
+    pub group: String,
+    /// The larger of Prism's total and Opal's, as Prism doesn't see sessions it wasn't open
+    /// for and Opal doesn't see launches that didn't go through it.
+    pub total_secs: u64,
+    pub opal_secs: u64,
+    pub sessions: usize,
+    pub longest_session_secs: u64,
+    /// Milliseconds since the epoch.
+    pub last_played: Option<u64>,
+}
+
+/// What the charts are about.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum StatsFilter {
+    #[default]
+    All,
+    Group(String),
+    Instance(String),
+}
+impl StatsFilter {
+    pub fn label(&self) -> String {
+        match self {
+            StatsFilter::All => String::from("All instances"),
+            StatsFilter::Group(group) => format!("Group: {}", group),
+            StatsFilter::Instance(folder) => folder.clone(),
+        }
+    }
+
+    pub fn matches(&self, session: &Session) -> bool {
+        match self {
+            StatsFilter::All => true,
+            StatsFilter::Group(group) => session.group == *group,
+            StatsFilter::Instance(folder) => session.instance == *folder,
+        }
+    }
+
+    pub fn matches_pack(&self, pack: &PackStats) -> bool {
+        match self {
+            StatsFilter::All => true,
+            StatsFilter::Group(group) => pack.group == *group,
+            StatsFilter::Instance(folder) => pack.instance == *folder,
+        }
+    }
+}
+
+/// Stats for every instance, and for removed instances that Opal has sessions of, most played first.
+pub fn pack_stats(instances: &[Instance], sessions: &[Session]) -> Vec<PackStats> {
+    let mut packs: Vec<PackStats> = instances.iter()
+        .map(|inst| PackStats {
+            instance: inst.folder_name.clone(),
+            name: inst.display_name().to_string(),
+            group: inst.group.clone(),
+            total_secs: inst.total_time_played,
+            last_played: inst.last_launch,
+            ..Default::default()
+        })
+        .collect();
+
+    for session in sessions {
+        let position = match packs.iter().position(|p| p.instance == session.instance) {
+            Some(i) => i,
+            None => {
+                packs.push(PackStats {
+                    instance: session.instance.clone(),
+                    name: session.name.clone(),
+                    group: session.group.clone(),
+                    ..Default::default()
+                });
+                packs.len() - 1
+            }
+        };
+        let pack = &mut packs[position];
+        pack.opal_secs += session.duration_secs;
+        pack.sessions += 1;
+        pack.longest_session_secs = pack.longest_session_secs.max(session.duration_secs);
+        pack.last_played = pack.last_played.max(Some(session.ended()));
+    }
+    for pack in packs.iter_mut() {
+        pack.total_secs = pack.total_secs.max(pack.opal_secs);
+    }
+
+    packs.sort_by(|a, b| b.total_secs.cmp(&a.total_secs).then_with(|| a.name.cmp(&b.name)));
+    packs
+}
+
+/// Total play time per group, most played first. Instances without a group are under "".
+pub fn group_totals(packs: &[PackStats]) -> Vec<(String, u64)> {
+    let mut totals: HashMap<&str, u64> = HashMap::new();
+    for pack in packs {
+        *totals.entry(&pack.group).or_default() += pack.total_secs;
+    }
+    let mut totals: Vec<(String, u64)> = totals.into_iter().map(|(group, secs)| (group.to_string(), secs)).collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
+
+/// Seconds played in each of the last `weeks` weeks, this week first. Only Opal's sessions
+/// have dates, so launches that didn't go through it are missing.
+pub fn weekly_play(sessions: &[Session], filter: &StatsFilter, weeks: usize, now_ms: u64) -> Vec<u64> {
+    let mut totals = vec![0; weeks];
+    for session in sessions.iter().filter(|s| filter.matches(s)) {
+        let week = (now_ms.saturating_sub(session.started) / WEEK_MS) as usize;
+        if let Some(total) = totals.get_mut(week) {
+            *total += session.duration_secs;
+        }
+    }
+    totals
+}
+
+/// The `count` longest sessions, longest first.
+pub fn longest_sessions<'a>(sessions: &'a [Session], filter: &StatsFilter, count: usize) -> Vec<&'a Session> {
+    let mut longest: Vec<&Session> = sessions.iter().filter(|s| filter.matches(s)).collect();
+    longest.sort_by_key(|s| std::cmp::Reverse(s.duration_secs));
+    longest.truncate(count);
+    longest
+}
+
+pub fn to_csv(packs: &[PackStats]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for pack in packs {
+        let fields = [
+            csv_field(&pack.instance), csv_field(&pack.name), csv_field(&pack.group),
+            pack.total_secs.to_string(), pack.opal_secs.to_string(), pack.sessions.to_string(),
+            pack.longest_session_secs.to_string(), pack.last_played.map(|t| t.to_string()).unwrap_or_default(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// The per-instance stats with every session, for other tools.
+pub fn to_json(packs: &[PackStats], sessions: &[Session]) -> serde_json::Result<String> {
+    #[derive(Serialize)]
+    struct Export<'a> {
+        instances: &'a [PackStats],
+        sessions: &'a [Session],
+    }
+    serde_json::to_string_pretty(&Export { instances: packs, sessions })
+}
@@ -0,0 +1,157 @@
+use std::io;
+use eframe::egui::*;
+use log::error;
+use super::ui::{TabPage, format_play_time, format_time_ago};
+use super::app::{AppModel, APP_HEADER_PADDING};
+use super::rules::now_ms;
+use super::stats::{group_totals, longest_sessions, pack_stats, to_csv, to_json, weekly_play, PackStats, StatsFilter};
+
+const WEEKS_SHOWN : usize = 12;
+const TOP_COUNT : usize = 10;
+const CHART_HEIGHT : f32 = 120.0;
+const WEEK_BAR_WIDTH : f32 = 28.0;
+const BAR_WIDTH : f32 = 240.0;
+const BAR_HEIGHT : f32 = 14.0;
+
+/// The “Stats” tab: play time per instance and group, from Prism and Opal's own sessions.
+#[derive(Default)]
+pub struct StatsPage {
+    filter: StatsFilter,
+}
+
+impl TabPage for StatsPage {
+    fn id(&self) -> &'static str { "stats" }
+    fn label(&self) -> &'static str { "Stats" }
+
+    fn ui(&mut self, ui: &mut Ui, model: &mut AppModel) {
+        ui.heading("Stats");
+
+        ui.add_space(APP_HEADER_PADDING);
+
+        let sessions = &model.playtime.sessions;
+        let packs = pack_stats(&model.instances, sessions);
+        let mut groups: Vec<String> = packs.iter().map(|p| p.group.clone()).filter(|g| !g.is_empty()).collect();
+        groups.sort();
+        groups.dedup();
+
+        ui.horizontal(|ui| {
+            let name_label = ui.label("Show:");
+            ComboBox::from_id_salt("stats_filter")
+                .selected_text(self.filter.label())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.filter, StatsFilter::All, StatsFilter::All.label());
+                    for group in groups.iter() {
+                        let option = StatsFilter::Group(group.clone());
+                        let label = option.label();
+                        ui.selectable_value(&mut self.filter, option, label);
+                    }
+                    for pack in packs.iter() {
+                        ui.selectable_value(&mut self.filter, StatsFilter::Instance(pack.instance.clone()), &pack.name);
+                    }
+                })
+                .response
+                .labelled_by(name_label.id);
+            ui.separator();
+            if ui.button("Export CSV").clicked() {
+                export(model, &packs, false);
+            }
+            if ui.button("Export JSON").clicked() {
+                export(model, &packs, true);
+            }
+        });
+
+        ui.separator();
+
+        let sessions = &model.playtime.sessions;
+        let now = now_ms();
+        ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+            ui.strong("Time Played per Week");
+            ui.weak("Only launches through Opal have dates.");
+            weekly_chart(ui, &weekly_play(sessions, &self.filter, WEEKS_SHOWN, now));
+
+            ui.add_space(12.0);
+            ui.strong("Most Played");
+            let shown: Vec<(String, u64)> = packs.iter()
+                .filter(|p| self.filter.matches_pack(p) && p.total_secs > 0)
+                .take(TOP_COUNT)
+                .map(|p| (p.name.clone(), p.total_secs))
+                .collect();
+            if shown.is_empty() { ui.weak("Nothing played yet."); }
+            bar_rows(ui, "most_played", &shown);
+
+            if self.filter == StatsFilter::All {
+                ui.add_space(12.0);
+                ui.strong("By Group");
+                let totals: Vec<(String, u64)> = group_totals(&packs).into_iter()
+                    .filter(|(_, secs)| *secs > 0)
+                    .map(|(group, secs)| (if group.is_empty() { String::from("(No group)") } else { group }, secs))
+                    .collect();
+                bar_rows(ui, "by_group", &totals);
+            }
+
+            ui.add_space(12.0);
+            ui.strong("Longest Sessions");
+            let longest = longest_sessions(sessions, &self.filter, TOP_COUNT);
+            if longest.is_empty() { ui.weak("No sessions recorded yet."); }
+            Grid::new("longest_sessions").num_columns(3).spacing([12.0, 4.0]).striped(true).show(ui, |ui| {
+                for session in longest {
+                    ui.label(&session.name);
+                    ui.label(format_play_time(session.duration_secs));
+                    ui.weak(format_time_ago(Some(session.started), now));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
+
+/// One bar per week, oldest on the left.
+fn weekly_chart(ui: &mut Ui, weeks: &[u64]) {
+    let max = weeks.iter().copied().max().unwrap_or(0).max(1);
+    let color = ui.visuals().selection.bg_fill;
+    ui.horizontal(|ui| {
+        for (ago, secs) in weeks.iter().enumerate().rev() {
+            let (rect, response) = ui.allocate_exact_size(vec2(WEEK_BAR_WIDTH, CHART_HEIGHT), Sense::hover());
+            let height = (rect.height() * (*secs as f32 / max as f32)).max(1.0);
+            ui.painter().rect_filled(Rect::from_min_max(pos2(rect.left(), rect.bottom() - height), rect.max), 2.0, color);
+            let when = match ago {
+                0 => String::from("This week"),
+                1 => String::from("Last week"),
+                n => format!("{} weeks ago", n),
+            };
+            response.on_hover_text(format!("{}: {}", when, format_play_time(*secs)));
+        }
+    });
+}
+
+/// A label, a bar relative to the largest value and the play time, per row.
+fn bar_rows(ui: &mut Ui, id_salt: &str, rows: &[(String, u64)]) {
+    let max = rows.iter().map(|(_, secs)| *secs).max().unwrap_or(0).max(1);
+    let color = ui.visuals().selection.bg_fill;
+    Grid::new(id_salt).num_columns(3).spacing([12.0, 4.0]).show(ui, |ui| {
+        for (label, secs) in rows {
+            ui.label(label);
+            let (rect, _) = ui.allocate_exact_size(vec2(BAR_WIDTH, BAR_HEIGHT), Sense::hover());
+            let width = rect.width() * (*secs as f32 / max as f32);
+            ui.painter().rect_filled(Rect::from_min_size(rect.min, vec2(width, rect.height())), 2.0, color);
+            ui.label(format_play_time(*secs));
+            ui.end_row();
+        }
+    });
+}
+
+fn export(model: &mut AppModel, packs: &[PackStats], json: bool) {
+    let extension = if json { "json" } else { "csv" };
+    let Some(path) = rfd::FileDialog::new()
+        .set_file_name(format!("opal-stats.{}", extension))
+        .add_filter(extension.to_uppercase(), &[extension])
+        .save_file() else { return };
+
+    let contents = match json {
+        true => to_json(packs, &model.playtime.sessions).map_err(io::Error::other),
+        false => Ok(to_csv(packs)),
+    };
+    if let Err(e) = contents.and_then(|contents| std::fs::write(&path, contents)) {
+        error!("Couldn't export the stats! {}", e);
+    }
+}
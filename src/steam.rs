@@ -1,6 +1,6 @@
 use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
 use std::{
-    ffi::OsStr, fs::{read, write}, io, process::Command, thread::sleep, time::{Duration, Instant}
+    ffi::OsStr, io, process::Command, thread::sleep, time::{Duration, Instant}
 };
 use log::{
     info
@@ -11,11 +11,18 @@ use steam_shortcuts_util::{
     shortcut::{Shortcut, ShortcutOwned},
     app_id_generator::calculate_app_id_for_shortcut,
 };
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use steamlocate::*;
+use serde::{Serialize, Deserialize};
+use super::history::PlannedShortcut;
+use super::platform::{FileIo, SteamLocator};
+use super::merge::{MergePolicy, ShortcutField};
+use super::sync::{launch_target, launched_instance, position_by_target, LaunchTarget};
 
 const DEFAULT_SHORTCUTS_PATH_PREAMBLE: &str = "userdata";
-const DEFAULT_SHORTCUTS_PATH: &str = "config\\shortcuts.vdf";
+const DEFAULT_SHORTCUTS_PATH: [&str; 2] = ["config", "shortcuts.vdf"];
+/// `<Steam>/config/loginusers.vdf`, the accounts that logged in to Steam on this machine.
+const LOGIN_USERS_PATH: [&str; 2] = ["config", "loginusers.vdf"];
 
 // Wait until Steam has stopped
 pub fn ensure_steam_stopped(timeout : Duration) -> io::Result<()> {
@@ -57,10 +64,11 @@ pub fn ensure_steam_stopped(timeout : Duration) -> io::Result<()> {
         }
     }
     info!("Steam is stopped");
-    return Ok(());
+    Ok(())
 }
 
-/// Launch Steam (platform-specific).
+/// Launch Steam (platform-specific). Each platform's block returns on its own.
+#[allow(clippy::needless_return)]
 pub fn start_steam() -> io::Result<()> {
     #[cfg(target_os = "windows")]
     {
@@ -118,6 +126,18 @@ pub fn start_steam() -> io::Result<()> {
     }
 }
 
+/// Whether Steam is running right now.
+pub fn is_steam_running() -> bool {
+    #[cfg(target_os = "windows")]
+    let steam_name = "steam.exe";
+    #[cfg(target_family = "unix")]
+    let steam_name = "steam";
+
+    let mut s = System::new();
+    s.refresh_processes(ProcessesToUpdate::All, true);
+    return s.processes_by_name(OsStr::new(steam_name)).next().is_some();
+}
+
 /// Wait until Steam shows up in the process list (or time out).
 pub fn ensure_steam_started(timeout: Duration) -> io::Result<()> {
     #[cfg(target_os = "windows")]
@@ -154,6 +174,14 @@ pub struct DesiredShortcut {
     pub tags: Vec<String>,
     // Optional: populate if you use Steam's "Shortcut Path" field
     pub shortcut_path: String,
+    pub is_hidden: bool,
+    pub allow_overlay: bool,
+    pub allow_desktop_config: bool,
+    pub open_vr: bool,
+    /// Seconds since the epoch, from Opal's play time database. 0 if unknown.
+    pub last_play_time: u32,
+    /// Fields the user set for this instance in Opal, which win over what is in Steam.
+    pub explicit: Vec<ShortcutField>,
 }
 impl DesiredShortcut {
   pub fn make_owned(&self, order: usize) -> ShortcutOwned {
@@ -176,63 +204,232 @@ impl DesiredShortcut {
       // Compute app_id using the borrowed view of our owned struct
       owned.app_id = calculate_app_id_for_shortcut(&owned.borrow());
 
+      owned.is_hidden = self.is_hidden;
+      owned.allow_desktop_config = self.allow_desktop_config;
+      owned.allow_overlay = self.allow_overlay;
+      owned.open_vr = self.open_vr as u32;
+
       // Sensible defaults (match crate’s intent)
-      owned.is_hidden = false;
-      owned.allow_desktop_config = true;
-      owned.allow_overlay = true;
-      owned.open_vr = 0;
       owned.dev_kit = 0;
       owned.dev_kit_overrite_app_id = 0;
-      owned.last_play_time = 0;
+      owned.last_play_time = self.last_play_time;
 
       owned
   }
 }
 
-pub fn write_steam_shortcuts(path: &Path, desired_vec: Vec<DesiredShortcut>) -> io::Result<()> {
+/// Read and parse a `shortcuts.vdf` file.
+pub fn read_steam_shortcuts(files: &dyn FileIo, path: &Path) -> io::Result<Vec<ShortcutOwned>> {
+    // Immediately break lifetimes with `to_owned`.
+    let bytes = files.read(path)?;
+    let parsed: Vec<Shortcut> = parse_shortcuts(bytes.as_slice())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("parse: {e}")))?;
+    let mut shortcuts: Vec<ShortcutOwned> = parsed.into_iter().map(|s| s.to_owned()).collect();
+    fix_numeric_fields(&bytes, &mut shortcuts);
+    Ok(shortcuts)
+}
+
+/// `parse_shortcuts` takes a number whose first byte is 0x01 for a three-byte one, so
+/// `IsHidden` 1 reads as 0 and app_ids ending in 0x01 lose that byte. Steam always writes
+/// four bytes, so these fields are read again from the file.
+type SetField = fn(&mut ShortcutOwned, u32);
+const NUMERIC_FIELDS : [(&str, SetField); 6] = [
+    ("appid", |s, v| s.app_id = v),
+    ("IsHidden", |s, v| s.is_hidden = v != 0),
+    ("openvr", |s, v| s.open_vr = v),
+    ("Devkit", |s, v| s.dev_kit = v),
+    ("DevkitOverrideAppID", |s, v| s.dev_kit_overrite_app_id = v),
+    ("LastPlayTime", |s, v| s.last_play_time = v),
+];
+
+fn fix_numeric_fields(bytes: &[u8], shortcuts: &mut [ShortcutOwned]) {
+    for (name, set) in NUMERIC_FIELDS {
+        let key = [&[0x02], name.as_bytes(), &[0x00]].concat();
+        let values: Vec<u32> = bytes.windows(key.len() + 4)
+            .filter(|w| w[..key.len()].eq_ignore_ascii_case(&key))
+            .map(|w| u32::from_le_bytes([w[key.len()], w[key.len() + 1], w[key.len() + 2], w[key.len() + 3]]))
+            .collect();
+        // If some shortcut doesn't have the field, the values can't be matched to the shortcuts.
+        if values.len() != shortcuts.len() { continue; }
+        for (shortcut, value) in shortcuts.iter_mut().zip(values) {
+            set(shortcut, value);
+        }
+    }
+}
+
+/// Where `write_steam_shortcuts` puts shortcuts that aren't in Steam yet.
+/// Shortcuts that are already there never move.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShortcutPlacement {
+    /// At the end, in the order they were exported.
+    #[default]
+    End,
+    /// At the end, sorted by name.
+    EndSortedByName,
+    /// Right after the last shortcut Opal made, so Opal's shortcuts stay together.
+    WithOpalShortcuts,
+}
+impl ShortcutPlacement {
+    pub const ALL : [ShortcutPlacement; 3] = [
+        ShortcutPlacement::End, ShortcutPlacement::EndSortedByName, ShortcutPlacement::WithOpalShortcuts,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShortcutPlacement::End => "At the end",
+            ShortcutPlacement::EndSortedByName => "At the end, sorted by name",
+            ShortcutPlacement::WithOpalShortcuts => "After the other Opal shortcuts",
+        }
+    }
+}
+
+/// Upsert `desired_vec` into the shortcuts at `path`. Shortcuts that are already in Steam
+/// are merged field by field according to `policy` and stay where they are; new ones are
+/// inserted according to `placement`. `previous` is the plan of the last export, see
+/// `MergePolicy::merge`. Returns the app_id of every desired shortcut, in order.
+pub fn write_steam_shortcuts(files: &dyn FileIo, path: &Path, desired_vec: Vec<DesiredShortcut>, policy: &MergePolicy, placement: ShortcutPlacement, previous: &[PlannedShortcut]) -> io::Result<Vec<u32>> {
 
-        if !path.exists() { return Err(io::Error::last_os_error()) };
+        if !files.exists(path) {
+            return Err(io::Error::new(io::ErrorKind::NotFound,
+                format!("{} doesn't exist. Start Steam with this user once so it's created.", path.display())));
+        }
         // Make sure the content exists and can be successfully read. If not, print out error.
-        // Immediately break lifetimes with `to_owned`.
-        let mut existing_owned: Vec<ShortcutOwned> = {
-            let bytes = read(path).unwrap_or_default();
-            let parsed: Vec<Shortcut> = parse_shortcuts(bytes.as_slice())
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("parse: {e}")))
-                .unwrap();
-            parsed.into_iter().map(|s| s.to_owned()).collect()
-        };
-
-        // Index existing by app_id (stable identifier for Steam assets).
-        let mut by_id: std::collections::BTreeMap<u32, ShortcutOwned> =
-            existing_owned.drain(..).map(|s| (s.app_id, s)).collect();
-        
+        let mut shortcuts: Vec<ShortcutOwned> = read_steam_shortcuts(files, path)?;
+        let mut new_shortcuts = Vec::new();
+        let mut app_ids = Vec::new();
+        let mut targets = Vec::new();
+        let mut updated = HashSet::new();
 
         for (i, d) in desired_vec.iter().enumerate() {
             let sc = d.make_owned(i);
-            // If you prefer "app name + exe" as the identity instead of app_id, change this keying.
-            by_id.insert(sc.app_id, sc);
+            // Match by app_id (stable identifier for Steam assets). A shortcut that launches
+            // the same instance (and server) also counts, and keeps its app_id even if the exe or
+            // name it was computed from changed since (renames, repaired shortcuts).
+            let target = launch_target(&sc);
+            let position = shortcuts.iter().position(|s| s.app_id == sc.app_id)
+                .or_else(|| position_by_target(&shortcuts, target.as_ref()?));
+            targets.extend(target);
+
+            match position {
+                Some(position) => {
+                    let existing = &shortcuts[position];
+                    let planned = previous.iter().find(|p| p.app_id == existing.app_id);
+                    let mut merged = policy.merge(existing, sc, &d.explicit, planned);
+                    merged.app_id = existing.app_id;
+                    app_ids.push(merged.app_id);
+                    updated.insert(position);
+                    shortcuts[position] = merged;
+                }
+                None => {
+                    app_ids.push(sc.app_id);
+                    new_shortcuts.push(sc);
+                }
+            }
         }
-        
-        // Rebuild a stable, ordered list and fix the `order` field.
-        let mut final_owned: Vec<ShortcutOwned> = by_id.into_values().collect();
-        final_owned.sort_by(|a, b| a.app_name.cmp(&b.app_name)); // or whatever ordering you like
-        for (i, s) in final_owned.iter_mut().enumerate() {
-            s.order = i.to_string();
+
+        // What's left for an account of something exported, with an account none of the desired
+        // shortcuts use, is from an account that was removed in Opal.
+        let stale: Vec<usize> = shortcuts.iter().enumerate()
+            .filter(|(i, s)| !updated.contains(i) && launch_target(s).is_some_and(|t| is_removed_account(&t, &targets)))
+            .map(|(i, _)| i)
+            .collect();
+        for i in stale.into_iter().rev() {
+            shortcuts.remove(i);
+        }
+
+        match placement {
+            ShortcutPlacement::End => shortcuts.append(&mut new_shortcuts),
+            ShortcutPlacement::EndSortedByName => {
+                new_shortcuts.sort_by_key(|s| s.app_name.to_lowercase());
+                shortcuts.append(&mut new_shortcuts);
+            }
+            ShortcutPlacement::WithOpalShortcuts => {
+                let after = shortcuts.iter().rposition(|s| launched_instance(s).is_some())
+                    .map(|i| i + 1)
+                    .unwrap_or(shortcuts.len());
+                shortcuts.splice(after..after, new_shortcuts);
+            }
         }
 
-        // Borrow-on-demand to serialize.
-        // NOTE: `shortcuts_to_bytes` wants `Vec<Shortcut<'_>>`, so produce a borrowed view.
-        let borrowed: Vec<Shortcut> = final_owned.iter().map(|s| s.borrow()).collect();
-        let out = shortcuts_to_bytes(&borrowed);
+        files.write(path, &shortcuts_to_vdf(&mut shortcuts))?;
+        Ok(app_ids)
+    }
+
+/// Whether `target` is for an account, and `targets` launch the same thing but never with
+/// that account.
+fn is_removed_account(target: &LaunchTarget, targets: &[LaunchTarget]) -> bool {
+    let Some(account) = &target.account else { return false };
+    let without_account = LaunchTarget { account: None, ..target.clone() };
+    let same_thing: Vec<&LaunchTarget> = targets.iter()
+        .filter(|t| LaunchTarget { account: None, ..(*t).clone() } == without_account)
+        .collect();
+    !same_thing.is_empty() && !same_thing.iter().any(|t| t.account.as_ref() == Some(account))
+}
+
+/// Apply `edit` to the shortcuts in `path` and write them back, keeping everything else
+/// (order, app_ids, fields Opal doesn't touch) as it was.
+pub fn edit_steam_shortcuts(files: &dyn FileIo, path: &Path, edit: impl FnOnce(&mut Vec<ShortcutOwned>)) -> io::Result<()> {
+    let mut shortcuts = read_steam_shortcuts(files, path)?;
+    edit(&mut shortcuts);
+    files.write(path, &shortcuts_to_vdf(&mut shortcuts))
+}
+
+/// Serialize shortcuts in the given order. `shortcuts_to_bytes` numbers the entries by
+/// their position and ignores the `order` field, so we sync the field to match; it only
+/// changes for shortcuts after an inserted or removed one.
+fn shortcuts_to_vdf(shortcuts: &mut [ShortcutOwned]) -> Vec<u8> {
+    for (i, s) in shortcuts.iter_mut().enumerate() {
+        s.order = i.to_string();
+    }
+
+    // Borrow-on-demand to serialize.
+    // NOTE: `shortcuts_to_bytes` wants `Vec<Shortcut<'_>>`, so produce a borrowed view.
+    let borrowed: Vec<Shortcut> = shortcuts.iter().map(|s| s.borrow()).collect();
+    shortcuts_to_bytes(&borrowed)
+}
+
+/// The Steam folder that `<Steam>/userdata/<user id>/config/shortcuts.vdf` is in.
+pub fn steam_dir_from_shortcuts(shortcuts_path: &Path) -> Option<&Path> {
+    shortcuts_path.parent()?.parent()?.parent()?.parent()
+}
 
-        // Write back to disk.
-        write(path, out)
+/// The user whose shortcuts Opal edits by default: the one who logged in to Steam last, or
+/// else the first one with a folder in `userdata`. User ids are the 32-bit account ids that
+/// name the `userdata` folders, not the 64-bit Steam ids in `loginusers.vdf`.
+pub fn default_steam_user(locator: &dyn SteamLocator, files: &dyn FileIo) -> io::Result<u32> {
+    let steam_directory = locator.steam_dir()?;
+    let login_users = files.read(&steam_directory.join(LOGIN_USERS_PATH.iter().collect::<PathBuf>()))
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok());
+    if let Some(id) = login_users.as_deref().and_then(most_recent_user) {
+        return Ok(id);
     }
 
-pub fn default_steam_shortcuts_path(id: u32) -> Result<PathBuf> {
-    let mut steam_directory = SteamDir::locate()?.path().to_owned();
+    let mut ids: Vec<u32> = files.list(&steam_directory.join(DEFAULT_SHORTCUTS_PATH_PREAMBLE))?.iter()
+        .filter_map(|dir| dir.file_name()?.to_str()?.parse().ok())
+        // 0 is where Steam keeps things from before anyone logged in.
+        .filter(|id| *id != 0)
+        .collect();
+    ids.sort();
+    ids.first().copied().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No Steam user has logged in on this machine"))
+}
+
+/// The account id of the user marked `MostRecent` in `loginusers.vdf`.
+fn most_recent_user(login_users: &str) -> Option<u32> {
+    let vdf = keyvalues_parser::Vdf::parse(login_users).ok()?;
+    vdf.value.get_obj()?.iter()
+        .find(|(_, values)| values.first()
+            .and_then(|user| user.get_obj()?.get("MostRecent")?.first()?.get_str())
+            .is_some_and(|recent| recent == "1"))
+        .and_then(|(steam_id, _)| steam_id.parse::<u64>().ok())
+        // The account id is the low half of the Steam id.
+        .map(|steam_id| steam_id as u32)
+}
+
+pub fn default_steam_shortcuts_path(locator: &dyn SteamLocator, id: u32) -> io::Result<PathBuf> {
+    let mut steam_directory = locator.steam_dir()?;
     steam_directory.push(DEFAULT_SHORTCUTS_PATH_PREAMBLE);
     steam_directory.push(format!("{}", id));
-    steam_directory.push(DEFAULT_SHORTCUTS_PATH);
-    return Ok(steam_directory)
+    steam_directory.extend(DEFAULT_SHORTCUTS_PATH);
+    Ok(steam_directory)
 }
\ No newline at end of file
@@ -0,0 +1,178 @@
+use std::path::Path;
+use steam_shortcuts_util::shortcut::ShortcutOwned;
+use super::instances::Instance;
+use super::steam::DesiredShortcut;
+
+const PRISMLAUNCHER_EXE_NAMES : [&str; 2] = ["prismlauncher.exe", "prismlauncher"];
+const OPAL_EXE_NAMES : [&str; 2] = ["opal.exe", "opal"];
+/// Opal's launch wrapper is `opal launch <PrismLauncher arguments>`.
+pub const LAUNCH_COMMAND : &str = "launch";
+
+/// How an instance relates to what is currently in `shortcuts.vdf`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SyncStatus {
+    #[default]
+    NotExported,
+    Exported,
+    /// There is a shortcut for the instance, but it differs from what Opal would write now.
+    OutOfDate(String),
+}
+impl SyncStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SyncStatus::NotExported => "Not exported",
+            SyncStatus::Exported => "Exported",
+            SyncStatus::OutOfDate(_) => "Out of date",
+        }
+    }
+
+    pub fn is_in_steam(&self) -> bool {
+        !matches!(self, SyncStatus::NotExported)
+    }
+
+    /// Used to sort the table by status.
+    pub fn rank(&self) -> u8 {
+        match self {
+            SyncStatus::NotExported => 0,
+            SyncStatus::OutOfDate(_) => 1,
+            SyncStatus::Exported => 2,
+        }
+    }
+}
+
+/// What an Opal shortcut launches: an instance, optionally straight into a server or world,
+/// and with which account.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LaunchTarget {
+    pub instance: String,
+    pub server: Option<String>,
+    /// The save folder name.
+    pub world: Option<String>,
+    /// The profile name of a PrismLauncher account.
+    pub account: Option<String>,
+}
+impl LaunchTarget {
+    pub fn instance(folder_name: &str) -> Self {
+        Self { instance: folder_name.to_string(), server: None, world: None, account: None }
+    }
+
+    /// The PrismLauncher arguments for this target, before any extra launch options.
+    pub fn launch_options(&self) -> String {
+        let mut options = format!("-l \"{}\"", self.instance);
+        if let Some(server) = &self.server {
+            options.push_str(&format!(" --server \"{}\"", server));
+        }
+        if let Some(world) = &self.world {
+            options.push_str(&format!(" --world \"{}\"", world));
+        }
+        if let Some(account) = &self.account {
+            options.push_str(&format!(" -a \"{}\"", account));
+        }
+        options
+    }
+}
+
+fn exe_name(shortcut: &ShortcutOwned) -> String {
+    let exe = shortcut.exe.trim_matches('"').replace('\\', "/");
+    exe.rsplit('/').next().unwrap_or_default().to_lowercase()
+}
+
+/// Whether `shortcut` goes through Opal's launch wrapper instead of starting PrismLauncher.
+pub fn is_wrapped(shortcut: &ShortcutOwned) -> bool {
+    OPAL_EXE_NAMES.contains(&exe_name(shortcut).as_str())
+        && split_launch_options(&shortcut.launch_options).first().is_some_and(|a| a == LAUNCH_COMMAND)
+}
+
+/// What `shortcut` launches, if it looks like an Opal shortcut (PrismLauncher, or Opal's
+/// launch wrapper, as the exe and `-l <instance>` in the launch options).
+pub fn launch_target(shortcut: &ShortcutOwned) -> Option<LaunchTarget> {
+    if !PRISMLAUNCHER_EXE_NAMES.contains(&exe_name(shortcut).as_str()) && !is_wrapped(shortcut) { return None; }
+
+    let args = split_launch_options(&shortcut.launch_options);
+    let value_of = |flags: [&str; 2]| {
+        let position = args.iter().position(|a| flags.contains(&a.as_str()))?;
+        args.get(position + 1).cloned()
+    };
+    Some(LaunchTarget {
+        instance: value_of(["-l", "--launch"])?,
+        server: value_of(["-s", "--server"]),
+        world: value_of(["-w", "--world"]),
+        account: value_of(["-a", "--profile"]),
+    })
+}
+
+/// The instance folder an Opal shortcut launches, if `shortcut` looks like one.
+pub fn launched_instance(shortcut: &ShortcutOwned) -> Option<String> {
+    launch_target(shortcut).map(|target| target.instance)
+}
+
+/// The shortcut in `existing` that launches `target`. A shortcut from before accounts were
+/// set for the instance counts for the account-specific target, so the first account's
+/// shortcut takes it over (with its app_id) instead of leaving it behind. Likewise, once
+/// the accounts are removed again, an account's shortcut counts for the target without one.
+pub fn position_by_target(existing: &[ShortcutOwned], target: &LaunchTarget) -> Option<usize> {
+    existing.iter().position(|s| launch_target(s).as_ref() == Some(target))
+        .or_else(|| {
+            let without_account = LaunchTarget { account: None, ..target.clone() };
+            match target.account {
+                Some(_) => existing.iter().position(|s| launch_target(s).as_ref() == Some(&without_account)),
+                None => existing.iter().position(|s| launch_target(s).is_some_and(|t| t.account.is_some()
+                    && LaunchTarget { account: None, ..t } == without_account)),
+            }
+        })
+}
+
+/// Split launch options like a shell would, honouring double quotes.
+pub fn split_launch_options(options: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut has_arg = false;
+    for c in options.chars() {
+        match c {
+            '"' => { quoted = !quoted; has_arg = true; }
+            c if c.is_whitespace() && !quoted => {
+                if has_arg { args.push(std::mem::take(&mut current)); }
+                has_arg = false;
+            }
+            c => { current.push(c); has_arg = true; }
+        }
+    }
+    if has_arg { args.push(current); }
+    args
+}
+
+/// Find the shortcut for an instance, first by app_id, then by the instance it launches
+/// (which still works after a rename or after Prism moved, both of which change the app_id).
+pub fn match_status(inst: &Instance, desired: &DesiredShortcut, existing: &[ShortcutOwned]) -> SyncStatus {
+    let wanted = desired.make_owned(0);
+
+    if let Some(found) = existing.iter().find(|s| s.app_id == wanted.app_id) {
+        // Launch options may carry extra arguments added in Steam after Opal's own.
+        return if !found.launch_options.starts_with(&wanted.launch_options) || found.start_dir != wanted.start_dir {
+            SyncStatus::OutOfDate(String::from("Launch options or start folder changed"))
+        } else {
+            SyncStatus::Exported
+        };
+    }
+
+    let target = launch_target(&wanted).unwrap_or_else(|| LaunchTarget::instance(&inst.folder_name));
+    let by_instance = position_by_target(existing, &target).map(|i| &existing[i]);
+    match by_instance {
+        // Same shortcut with an app_id kept from before a rename or repair.
+        Some(found) if found.exe == wanted.exe && found.app_name == wanted.app_name
+            && found.launch_options.starts_with(&wanted.launch_options) && found.start_dir == wanted.start_dir => SyncStatus::Exported,
+        Some(found) if found.exe != wanted.exe => SyncStatus::OutOfDate(format!("PrismLauncher moved (shortcut points to {})", found.exe)),
+        Some(found) if launch_target(found).is_some_and(|t| t.account != target.account) => SyncStatus::OutOfDate(String::from("Account changed")),
+        Some(found) => SyncStatus::OutOfDate(format!("Renamed (shortcut is called \"{}\")", found.app_name)),
+        None => SyncStatus::NotExported,
+    }
+}
+
+/// Opal shortcuts whose instance folder no longer exists in `instances_dir`.
+pub fn orphaned_shortcuts(existing: &[ShortcutOwned], instances_dir: &Path) -> Vec<ShortcutOwned> {
+    existing.iter()
+        .filter(|s| launched_instance(s).is_some_and(|folder| !instances_dir.join(folder).is_dir()))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+use super::instances::Instance;
+
+/// Placeholders understood by `render`, with a description for the settings page.
+pub const PLACEHOLDERS : [(&str, &str); 6] = [
+    ("name", "Name shown in PrismLauncher"),
+    ("folder", "Instance folder name"),
+    ("group", "PrismLauncher group"),
+    ("mc_version", "Minecraft version"),
+    ("loader", "Mod loader"),
+    ("loader_version", "Mod loader version"),
+];
+
+/// How shortcut names and tags are built from an instance.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct NamingSettings {
+    pub name_template: String,
+    /// One template per tag. Tags that render empty are dropped.
+    pub tag_templates: Vec<String>,
+    /// Instance folder name -> shortcut name, used instead of the template.
+    pub name_overrides: HashMap<String, String>,
+}
+impl Default for NamingSettings {
+    fn default() -> Self {
+        Self {
+            name_template: String::from("{folder}"),
+            tag_templates: vec![String::from("Installed"), String::from("Ready to play")],
+            name_overrides: HashMap::new(),
+        }
+    }
+}
+impl NamingSettings {
+    /// The shortcut name for `inst`. Falls back to the folder name if the template is broken
+    /// or renders empty, since a shortcut without a name can't be launched from Steam.
+    pub fn app_name(&self, inst: &Instance) -> String {
+        if let Some(name) = self.name_overrides.get(&inst.folder_name).filter(|n| !n.trim().is_empty()) {
+            return name.trim().to_string();
+        }
+        match render(&self.name_template, inst) {
+            Ok(name) if !name.is_empty() => name,
+            _ => inst.folder_name.clone(),
+        }
+    }
+
+    pub fn tags(&self, inst: &Instance) -> Vec<String> {
+        self.tag_templates.iter()
+            .filter_map(|t| render(t, inst).ok())
+            .filter(|t| !t.is_empty())
+            .collect()
+    }
+}
+
+fn placeholder_value(key: &str, inst: &Instance) -> Option<String> {
+    let value = match key {
+        "name" => inst.display_name().to_string(),
+        "folder" => inst.folder_name.clone(),
+        "group" => inst.group.clone(),
+        "mc_version" => inst.mc_version.clone().unwrap_or_default(),
+        "loader" => inst.loader.name().to_string(),
+        "loader_version" => inst.loader_version.clone().unwrap_or_default(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Replace `{placeholder}`s with values from `inst`. `{{` and `}}` are literal braces.
+/// Brackets left empty by missing values, like "()" in "{name} ({group})", are removed and
+/// whitespace is collapsed. Brackets typed without a placeholder in them are kept.
+pub fn render(template: &str, inst: &Instance) -> Result<String, String> {
+    let mut out = String::new();
+    let mut brackets: Vec<Bracket> = Vec::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => { chars.next(); literal(&mut out, &mut brackets, '{'); }
+            '}' if chars.peek() == Some(&'}') => { chars.next(); literal(&mut out, &mut brackets, '}'); }
+            '{' => {
+                let mut key = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => key.push(c),
+                        None => return Err(format!("Unclosed placeholder {{{}", key)),
+                    }
+                }
+                let Some(value) = placeholder_value(key.trim(), inst) else {
+                    return Err(format!("Unknown placeholder {{{}}}", key));
+                };
+                if let Some(bracket) = brackets.last_mut() {
+                    bracket.placeholders = true;
+                    bracket.content |= !value.trim().is_empty();
+                }
+                out.push_str(&value);
+            }
+            '}' => return Err(String::from("Unmatched }")),
+            c => literal(&mut out, &mut brackets, c),
+        }
+    }
+
+    Ok(out.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+const BRACKETS : [(char, char); 3] = [('(', ')'), ('[', ']'), ('<', '>')];
+
+/// A bracket of the template that is still open.
+struct Bracket {
+    /// Where the opening bracket is in the output.
+    start: usize,
+    close: char,
+    /// Whether there are placeholders in it, and anything besides whitespace.
+    placeholders: bool,
+    content: bool,
+}
+
+/// Add a character typed in the template, dropping a bracket pair that only had
+/// placeholders in it and all of them rendered empty.
+fn literal(out: &mut String, brackets: &mut Vec<Bracket>, c: char) {
+    if brackets.last().is_some_and(|b| b.close == c) {
+        let bracket = brackets.pop().expect("checked above");
+        if bracket.placeholders && !bracket.content {
+            out.truncate(bracket.start);
+            return;
+        }
+    } else if let Some((_, close)) = BRACKETS.iter().find(|(open, _)| *open == c) {
+        brackets.push(Bracket { start: out.len(), close: *close, placeholders: false, content: false });
+        out.push(c);
+        return;
+    }
+    if !c.is_whitespace() {
+        if let Some(bracket) = brackets.last_mut() { bracket.content = true; }
+    }
+    out.push(c);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atm9() -> Instance {
+        Instance {
+            folder_name: String::from("ATM9"),
+            name: String::from("All the Mods 9"),
+            group: String::from("Modded"),
+            mc_version: Some(String::from("1.20.1")),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn placeholders_are_replaced() {
+        assert_eq!(render("{name} ({ mc_version }) {{{folder}}}", &atm9()).unwrap(), "All the Mods 9 (1.20.1) {ATM9}");
+        assert_eq!(render("{nope}", &atm9()).unwrap_err(), "Unknown placeholder {nope}");
+        assert!(render("{name", &atm9()).is_err());
+        assert!(render("name}", &atm9()).is_err());
+    }
+
+    #[test]
+    fn brackets_around_missing_values_are_removed() {
+        let inst = Instance { group: String::new(), loader_version: None, ..atm9() };
+        assert_eq!(render("{name} ({group})", &inst).unwrap(), "All the Mods 9");
+        assert_eq!(render("{name} [{group} {loader_version}] <{mc_version}>", &inst).unwrap(), "All the Mods 9 <1.20.1>");
+        assert_eq!(render("{name} ({group} x)", &inst).unwrap(), "All the Mods 9 ( x)");
+    }
+
+    #[test]
+    fn literal_brackets_are_kept() {
+        assert_eq!(render("{name} () [] <>", &atm9()).unwrap(), "All the Mods 9 () [] <>");
+        let inst = Instance { name: String::from("Skyblock () [beta]"), group: String::new(), ..atm9() };
+        assert_eq!(render("{name} ({group})", &inst).unwrap(), "Skyblock () [beta]");
+    }
+}
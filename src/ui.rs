@@ -32,4 +32,45 @@ pub fn load_icon() -> Option<Arc<IconData>> {
 		width: icon_width,
 		height: icon_height,
 	}.into())
-}
\ No newline at end of file
+}
+
+/// Play time in Prism's style, e.g. "12h 5m".
+pub fn format_play_time(seconds: u64) -> String {
+	let (hours, minutes) = (seconds / 3600, (seconds % 3600) / 60);
+	if hours > 0 { format!("{hours}h {minutes}m") }
+	else if minutes > 0 { format!("{minutes}m") }
+	else if seconds > 0 { String::from("<1m") }
+	else { String::from("-") }
+}
+
+/// How long ago a UNIX timestamp in milliseconds was, e.g. "3 days ago".
+pub fn format_time_ago(timestamp_ms: Option<u64>, now_ms: u64) -> String {
+	let Some(timestamp_ms) = timestamp_ms else { return String::from("Never") };
+	let minutes = now_ms.saturating_sub(timestamp_ms) / 60_000;
+	let (amount, unit) = match minutes {
+		0 => return String::from("Just now"),
+		m if m < 60 => (m, "minute"),
+		m if m < 60 * 24 => (m / 60, "hour"),
+		m if m < 60 * 24 * 30 => (m / (60 * 24), "day"),
+		m if m < 60 * 24 * 365 => (m / (60 * 24 * 30), "month"),
+		m => (m / (60 * 24 * 365), "year"),
+	};
+	format!("{} {}{} ago", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+/// A UNIX timestamp in milliseconds as "2024-05-01 18:30:00", in UTC.
+pub fn format_date_time(timestamp_ms: u64) -> String {
+	let secs = timestamp_ms / 1000;
+	let (days, time) = ((secs / 86_400) as i64, secs % 86_400);
+	// Days to a civil date, from Howard Hinnant's `civil_from_days`.
+	let z = days + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z.rem_euclid(146_097);
+	let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + i64::from(month <= 2);
+	format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
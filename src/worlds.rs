@@ -0,0 +1,68 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use super::nbt;
+use super::servers::game_dir;
+
+const SAVES_DIR : &str = "saves";
+const LEVEL_FILE : &str = "level.dat";
+const ICON_FILE : &str = "icon.png";
+
+/// A singleplayer world that gets its own shortcut (`--world <folder>`).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct WorldShortcut {
+    /// The save's folder name, which is what PrismLauncher expects.
+    pub folder: String,
+    /// Shown after the instance name, e.g. "ATM9 — Skyblock".
+    pub name: String,
+}
+
+/// A world found in an instance's `saves` folder.
+#[derive(Debug, Clone)]
+pub struct World {
+    pub folder: String,
+    /// The name from `level.dat`, or the folder name if it couldn't be read.
+    pub name: String,
+    pub icon: Option<PathBuf>,
+    /// Milliseconds since the epoch, from `level.dat`.
+    pub last_played: Option<i64>,
+}
+
+/// The `saves` folder of the instance at `instance_path`, if it has one.
+pub fn saves_dir(instance_path: &Path) -> Option<PathBuf> {
+    game_dir(instance_path).map(|dir| dir.join(SAVES_DIR)).filter(|dir| dir.is_dir())
+}
+
+/// The icon Minecraft saved for a world, used as the shortcut icon.
+pub fn world_icon(instance_path: &Path, folder: &str) -> Option<PathBuf> {
+    saves_dir(instance_path).map(|dir| dir.join(folder).join(ICON_FILE)).filter(|p| p.is_file())
+}
+
+/// Every world of an instance, most recently played first.
+pub fn scan_worlds(instance_path: &Path) -> io::Result<Vec<World>> {
+    let Some(saves) = saves_dir(instance_path) else { return Ok(Vec::new()) };
+
+    let mut worlds = Vec::new();
+    for entry in std::fs::read_dir(saves)?.flatten() {
+        let path = entry.path();
+        let level = path.join(LEVEL_FILE);
+        if !level.is_file() { continue; }
+
+        let folder = entry.file_name().to_string_lossy().to_string();
+        // A world with a broken level.dat can still be launched, so list it anyway.
+        let data = nbt::read_file(&level).ok().and_then(|root| root.get("Data").cloned());
+        let name = data.as_ref()
+            .and_then(|d| d.get("LevelName")?.as_str().map(|n| n.trim().to_string()))
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| folder.clone());
+        worlds.push(World {
+            name,
+            icon: Some(path.join(ICON_FILE)).filter(|p| p.is_file()),
+            last_played: data.as_ref().and_then(|d| d.get("LastPlayed")?.as_i64()),
+            folder,
+        });
+    }
+    worlds.sort_by(|a, b| b.last_played.cmp(&a.last_played).then_with(|| a.name.cmp(&b.name)));
+    Ok(worlds)
+}
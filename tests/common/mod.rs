@@ -0,0 +1,232 @@
+//! A fake PrismLauncher folder, a fake Steam folder and a model wired to them, plus fakes for
+//! finding Steam, its process and its files.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use steam_shortcuts_util::{parse_shortcuts, shortcuts_to_bytes, shortcut::{Shortcut, ShortcutOwned}};
+use opal::app::AppModel;
+use opal::platform::{FileIo, Platform, SteamLocator, SteamProcess};
+use opal::settings_page::Config;
+
+pub const STEAM_USER : u32 = 123;
+
+/// A folder under the system's temp folder, deleted again when dropped.
+pub struct TempDir(PathBuf);
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("opal-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A Steam installation at a known folder, or none at all.
+#[derive(Debug, Clone, Default)]
+pub struct FakeSteamLocator {
+    pub dir: Option<PathBuf>,
+}
+impl SteamLocator for FakeSteamLocator {
+    fn steam_dir(&self) -> io::Result<PathBuf> {
+        self.dir.clone().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Steam isn't installed"))
+    }
+}
+
+/// What happened to a `FakeSteam`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SteamEvent {
+    Stopped,
+    Started,
+}
+
+#[derive(Debug, Default)]
+struct FakeSteamState {
+    running: bool,
+    events: Vec<SteamEvent>,
+}
+
+/// A Steam that only remembers whether it runs. Clones share their state, so a test can keep
+/// one and give the other to the model.
+#[derive(Debug, Clone, Default)]
+pub struct FakeSteam {
+    state: Arc<Mutex<FakeSteamState>>,
+}
+impl FakeSteam {
+    pub fn running() -> Self {
+        let steam = Self::default();
+        steam.state.lock().unwrap().running = true;
+        steam
+    }
+
+    /// Every stop and start, oldest first.
+    pub fn events(&self) -> Vec<SteamEvent> {
+        self.state.lock().unwrap().events.clone()
+    }
+}
+impl SteamProcess for FakeSteam {
+    fn is_running(&self) -> bool {
+        self.state.lock().unwrap().running
+    }
+
+    fn stop(&self, _timeout: Duration) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.running = false;
+        state.events.push(SteamEvent::Stopped);
+        Ok(())
+    }
+
+    fn start(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.running = true;
+        state.events.push(SteamEvent::Started);
+        Ok(())
+    }
+
+    fn wait_until_started(&self, _timeout: Duration) -> io::Result<()> {
+        match self.is_running() {
+            true => Ok(()),
+            false => Err(io::Error::new(io::ErrorKind::TimedOut, "Steam did not start in time")),
+        }
+    }
+}
+
+/// Files kept in memory. Clones share their files. Folders aren't kept, they exist as long
+/// as there are files in them.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFiles {
+    files: Arc<Mutex<HashMap<PathBuf, Vec<u8>>>>,
+}
+impl MemoryFiles {
+    pub fn insert(&self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) {
+        self.files.lock().unwrap().insert(path.into(), contents.into());
+    }
+
+    pub fn get(&self, path: &Path) -> Option<Vec<u8>> {
+        self.files.lock().unwrap().get(path).cloned()
+    }
+
+    /// Every file, in no particular order.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} doesn't exist", path.display()))
+}
+
+impl FileIo for MemoryFiles {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.get(path).ok_or_else(|| not_found(path))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.insert(path, contents);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.files.lock().unwrap().remove(path).map(|_| ()).ok_or_else(|| not_found(path))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        let contents = files.remove(from).ok_or_else(|| not_found(from))?;
+        files.insert(to.to_path_buf(), contents);
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.lock().unwrap().keys().any(|file| file.starts_with(path))
+    }
+
+    fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut entries: Vec<PathBuf> = self.files.lock().unwrap().keys()
+            .filter_map(|file| Some(dir.join(file.strip_prefix(dir).ok()?.components().next()?)))
+            .collect();
+        entries.sort();
+        entries.dedup();
+        Ok(entries)
+    }
+}
+
+/// PrismLauncher with "All the Mods 9" in the group "Modded" and an ungrouped "Vanilla".
+pub fn fake_prism(root: &Path) -> PathBuf {
+    let prism = root.join("prism");
+    let instances = prism.join("instances");
+    let packs = [
+        ("ATM9", "All the Mods 9", r#"[{"uid":"net.minecraft","version":"1.20.1"},{"uid":"net.neoforged","version":"47.1"}]"#),
+        ("Vanilla", "Vanilla", r#"[{"uid":"net.minecraft","version":"1.21"}]"#),
+    ];
+    for (folder, name, components) in packs {
+        let dir = instances.join(folder);
+        std::fs::create_dir_all(dir.join("minecraft")).unwrap();
+        std::fs::write(dir.join("instance.cfg"), format!("[General]\nname={}\ntotalTimePlayed=60\n", name)).unwrap();
+        std::fs::write(dir.join("mmc-pack.json"), format!(r#"{{"components":{}}}"#, components)).unwrap();
+    }
+    std::fs::write(instances.join("instgroups.json"),
+        r#"{"formatVersion":"1","groups":{"Modded":{"hidden":false,"instances":["ATM9"]}}}"#).unwrap();
+    prism
+}
+
+/// A Steam folder with one user, whose `shortcuts.vdf` has a shortcut of their own.
+/// Returns the path of `shortcuts.vdf`.
+pub fn fake_steam(root: &Path) -> PathBuf {
+    let config = root.join("steam").join("userdata").join(STEAM_USER.to_string()).join("config");
+    std::fs::create_dir_all(&config).unwrap();
+    let path = config.join("shortcuts.vdf");
+    std::fs::write(&path, user_shortcuts()).unwrap();
+    path
+}
+
+/// `shortcuts.vdf` with a single shortcut that has nothing to do with Opal.
+pub fn user_shortcuts() -> Vec<u8> {
+    let shortcut = Shortcut::new("0", "Some Game", "/games/some-game", "/games", "", "", "--fullscreen");
+    shortcuts_to_bytes(&vec![shortcut])
+}
+
+pub fn parse(bytes: &[u8]) -> Vec<ShortcutOwned> {
+    parse_shortcuts(bytes).unwrap().into_iter().map(|s| s.to_owned()).collect()
+}
+
+/// A model for the fake PrismLauncher and Steam under `root`, with everything selected.
+/// Opal's own data goes to `root/data`.
+pub fn fake_model(root: &Path, steam: FakeSteam, files: Box<dyn FileIo>) -> AppModel {
+    let mut model = AppModel {
+        config: Config {
+            prism_main_path: root.join("prism"),
+            steam_shortcuts_path: root.join("steam").join("userdata").join(STEAM_USER.to_string()).join("config").join("shortcuts.vdf"),
+            user_id: STEAM_USER,
+            ..Default::default()
+        },
+        platform: Platform {
+            locator: Box::new(FakeSteamLocator { dir: Some(root.join("steam")) }),
+            steam: Box::new(steam),
+            files,
+            data_dir: Some(root.join("data")),
+        },
+        ..Default::default()
+    };
+    model.update_instances();
+    for inst in model.instances.iter_mut() {
+        inst.checked = true;
+    }
+    model
+}
@@ -0,0 +1,233 @@
+//! The whole export, from PrismLauncher's instance list to `shortcuts.vdf` and the grid folder,
+//! against a fake PrismLauncher and Steam on disk and a fake Steam process.
+mod common;
+
+use std::path::Path;
+use opal::crash::capture_crash;
+use opal::doctor::{apply_fixes, Fix};
+use opal::instance_options::InstanceOptions;
+use opal::launch::{record_session, GameExit};
+use opal::playtime::Session;
+use opal::platform::DiskFiles;
+use opal::rules::{RuleAction, RuleCondition, RuleSet, SelectionRule};
+use opal::sync::{launched_instance, SyncStatus};
+use common::*;
+
+#[test]
+fn export_writes_shortcuts_with_steam_closed() {
+    let dir = TempDir::new("export");
+    fake_prism(dir.path());
+    let shortcuts_path = fake_steam(dir.path());
+    let steam = FakeSteam::running();
+    let mut model = fake_model(dir.path(), steam.clone(), Box::new(DiskFiles));
+    assert_eq!(model.instances.len(), 2);
+
+    model.export_to_steam().unwrap();
+
+    assert_eq!(steam.events(), [SteamEvent::Stopped, SteamEvent::Started]);
+    let shortcuts = parse(&std::fs::read(&shortcuts_path).unwrap());
+    assert_eq!(shortcuts.len(), 3);
+    assert_eq!(shortcuts[0].app_name, "Some Game");
+    let atm9 = shortcuts.iter().find(|s| launched_instance(s).as_deref() == Some("ATM9")).unwrap();
+    assert_eq!(atm9.exe, model.prism_exe_path());
+    assert_eq!(atm9.launch_options, "-l \"ATM9\"");
+    assert!(shortcuts.iter().any(|s| launched_instance(s).as_deref() == Some("Vanilla")));
+    assert!(model.instances.iter().all(|i| i.status == SyncStatus::Exported));
+    assert!(model.orphaned_shortcuts.is_empty());
+}
+
+#[test]
+fn exporting_twice_keeps_one_shortcut_per_instance() {
+    let dir = TempDir::new("export-twice");
+    fake_prism(dir.path());
+    let shortcuts_path = fake_steam(dir.path());
+    let mut model = fake_model(dir.path(), FakeSteam::running(), Box::new(DiskFiles));
+
+    model.export_to_steam().unwrap();
+    let first = std::fs::read(&shortcuts_path).unwrap();
+    model.export_to_steam().unwrap();
+
+    assert_eq!(std::fs::read(&shortcuts_path).unwrap(), first);
+}
+
+#[test]
+fn removed_instances_leave_orphaned_shortcuts() {
+    let dir = TempDir::new("orphans");
+    let prism = fake_prism(dir.path());
+    fake_steam(dir.path());
+    let mut model = fake_model(dir.path(), FakeSteam::running(), Box::new(DiskFiles));
+    model.export_to_steam().unwrap();
+
+    std::fs::remove_dir_all(prism.join("instances").join("Vanilla")).unwrap();
+    model.update_instances();
+
+    assert_eq!(model.instances.len(), 1);
+    assert_eq!(model.orphaned_shortcuts.len(), 1);
+    assert_eq!(launched_instance(&model.orphaned_shortcuts[0]).as_deref(), Some("Vanilla"));
+}
+
+#[test]
+fn exclude_rules_deselect_exported_instances() {
+    let dir = TempDir::new("exclude");
+    fake_prism(dir.path());
+    fake_steam(dir.path());
+    let mut model = fake_model(dir.path(), FakeSteam::running(), Box::new(DiskFiles));
+    model.export_to_steam().unwrap();
+
+    let exclude = SelectionRule { action: RuleAction::Exclude, condition: RuleCondition::NameGlob(String::from("Vanilla")), ..Default::default() };
+    model.config.selection_rules = RuleSet { rules: vec![exclude] };
+    model.update_instances();
+    model.keep_exported_selected();
+
+    let checked = |model: &opal::app::AppModel, folder: &str| model.instances.iter().find(|i| i.folder_name == folder).unwrap().checked;
+    // No rule matches ATM9, so it stays selected because it's in Steam.
+    assert!(checked(&model, "ATM9"));
+    assert!(!checked(&model, "Vanilla"));
+    let desired: Vec<String> = model.desired_shortcuts().into_iter().map(|s| s.app_name).collect();
+    assert_eq!(desired, ["ATM9"]);
+}
+
+#[test]
+fn undo_restores_shortcuts_and_removes_new_artwork() {
+    let dir = TempDir::new("undo");
+    fake_prism(dir.path());
+    let shortcuts_path = fake_steam(dir.path());
+    let hero = dir.path().join("hero.png");
+    std::fs::write(&hero, b"png").unwrap();
+    let steam = FakeSteam::running();
+    let mut model = fake_model(dir.path(), steam.clone(), Box::new(DiskFiles));
+    let mut options = InstanceOptions::default();
+    options.artwork.hero = Some(hero);
+    model.config.instance_options.insert(String::from("ATM9"), options);
+
+    model.export_to_steam().unwrap();
+    let record = model.export_history.last().unwrap().clone();
+    assert_eq!(record.plan.len(), 2);
+    assert_eq!(record.created_files.len(), 1);
+    assert!(record.created_files[0].is_file());
+    assert!(record.created_files[0].starts_with(shortcuts_path.parent().unwrap().join("grid")));
+
+    model.undo_last_export().unwrap();
+
+    assert_eq!(std::fs::read(&shortcuts_path).unwrap(), user_shortcuts());
+    assert!(!record.created_files[0].exists());
+    assert!(model.export_history.exports.iter().all(|r| r.time != record.time));
+    assert!(model.instances.iter().all(|i| i.status == SyncStatus::NotExported));
+    assert_eq!(steam.events(), [SteamEvent::Stopped, SteamEvent::Started, SteamEvent::Stopped, SteamEvent::Started]);
+}
+
+#[test]
+fn doctor_re_exports_are_recorded() {
+    let dir = TempDir::new("re-export");
+    fake_prism(dir.path());
+    let shortcuts_path = fake_steam(dir.path());
+    let mut model = fake_model(dir.path(), FakeSteam::running(), Box::new(DiskFiles));
+    model.export_to_steam().unwrap();
+    let exported = std::fs::read(&shortcuts_path).unwrap();
+    let plan = model.last_plan().to_vec();
+    let options = InstanceOptions { extra_tags: vec![String::from("Favourite")], ..Default::default() };
+    model.config.instance_options.insert(String::from("ATM9"), options);
+
+    let fixes = [Fix::ReExport { folder_name: String::from("ATM9") }];
+    model.while_steam_closed(|model| apply_fixes(model, &fixes)).unwrap();
+
+    assert_eq!(model.export_history.exports.len(), 2);
+    let atm9 = model.last_plan().iter().find(|p| p.app_name == "ATM9").unwrap();
+    assert!(atm9.tags.contains(&String::from("Favourite")));
+    let vanilla = plan.iter().find(|p| p.app_name == "Vanilla").unwrap();
+    assert!(model.last_plan().contains(vanilla));
+
+    model.undo_last_export().unwrap();
+
+    assert_eq!(std::fs::read(&shortcuts_path).unwrap(), exported);
+    assert_eq!(model.last_plan(), plan);
+}
+
+#[test]
+fn sessions_prism_counted_are_not_added_again() {
+    let dir = TempDir::new("session");
+    fake_prism(dir.path());
+    let mut model = fake_model(dir.path(), FakeSteam::running(), Box::new(MemoryFiles::default()));
+    model.config.launch.sync_prism_play_time = true;
+    let cfg = |model: &opal::app::AppModel, folder: &str| {
+        let inst = model.instances.iter().find(|i| i.folder_name == folder).unwrap();
+        std::fs::read_to_string(inst.path.join("instance.cfg")).unwrap()
+    };
+    let session = |folder: &str| Session { instance: folder.to_string(), name: folder.to_string(), group: String::new(), started: 1_000, duration_secs: 30 };
+
+    let atm9 = model.instances.iter().find(|i| i.folder_name == "ATM9").unwrap();
+    record_session(&model, atm9, &session("ATM9")).unwrap();
+    assert!(cfg(&model, "ATM9").contains("totalTimePlayed=90\n"));
+
+    // Prism went from 60 to 100 seconds while the game ran.
+    let vanilla = model.instances.iter().find(|i| i.folder_name == "Vanilla").unwrap();
+    std::fs::write(vanilla.path.join("instance.cfg"), "[General]\nname=Vanilla\ntotalTimePlayed=100\n").unwrap();
+    record_session(&model, vanilla, &session("Vanilla")).unwrap();
+    assert!(cfg(&model, "Vanilla").contains("totalTimePlayed=100\n"));
+}
+
+#[test]
+fn abnormal_exits_are_crashes_without_a_report() {
+    let dir = TempDir::new("crash");
+    let prism = fake_prism(dir.path());
+    let atm9 = prism.join("instances").join("ATM9");
+    let data_dir = dir.path().join("data");
+    let session = Session { instance: String::from("ATM9"), name: String::from("All the Mods 9"), group: String::new(), started: 1_000, duration_secs: 30 };
+
+    assert!(capture_crash(&data_dir, &atm9, &session, GameExit::Code(0)).unwrap().is_none());
+    assert!(capture_crash(&data_dir, &atm9, &session, GameExit::Unknown).unwrap().is_none());
+    // Steam's "Exit game" stops the JVM with SIGTERM.
+    let killed = if cfg!(unix) { GameExit::Signal(15) } else { GameExit::Code(0) };
+    assert!(capture_crash(&data_dir, &atm9, &session, killed).unwrap().is_none());
+
+    let crash = capture_crash(&data_dir, &atm9, &session, GameExit::Code(1)).unwrap().unwrap();
+    assert_eq!(crash.exit.as_deref(), Some("exit code 1"));
+    assert!(crash.exception.is_none());
+    assert!(crash.archive.starts_with(&data_dir));
+}
+
+#[test]
+fn failed_export_is_not_recorded() {
+    let dir = TempDir::new("failed");
+    fake_prism(dir.path());
+    let mut model = fake_model(dir.path(), FakeSteam::running(), Box::new(DiskFiles));
+    model.update_export_history();
+    let exports = model.export_history.exports.len();
+
+    // There's no Steam folder, so there's no shortcuts.vdf to add to.
+    assert!(model.export_to_steam().is_err());
+
+    assert_eq!(model.export_history.exports.len(), exports);
+}
+
+#[test]
+fn export_to_in_memory_steam_files() {
+    let dir = TempDir::new("memory");
+    fake_prism(dir.path());
+    let hero = dir.path().join("hero.png");
+    std::fs::write(&hero, b"png").unwrap();
+    let files = MemoryFiles::default();
+    let mut model = fake_model(dir.path(), FakeSteam::running(), Box::new(files.clone()));
+    let shortcuts_path = model.config.steam_shortcuts_path.clone();
+    files.insert(&shortcuts_path, user_shortcuts());
+    let mut options = InstanceOptions::default();
+    options.artwork.hero = Some(hero);
+    model.config.instance_options.insert(String::from("ATM9"), options);
+
+    model.export_to_steam().unwrap();
+
+    let shortcuts = parse(&files.get(&shortcuts_path).unwrap());
+    assert_eq!(shortcuts.len(), 3);
+    assert!(!Path::new(&shortcuts_path).exists());
+    let app_ids = model.exported_app_ids().unwrap();
+    assert_eq!(app_ids.len(), 2);
+    let artwork = model.export_history.last().unwrap().created_files.clone();
+    assert_eq!(artwork.len(), 1);
+    assert_eq!(files.get(&artwork[0]).unwrap(), b"png");
+    assert!(!artwork[0].exists());
+
+    model.undo_last_export().unwrap();
+
+    assert_eq!(files.get(&shortcuts_path).unwrap(), user_shortcuts());
+    assert_eq!(files.paths(), [shortcuts_path]);
+}
@@ -0,0 +1,219 @@
+//! `shortcuts.vdf` handling and Steam's stop/start logic, against in-memory files and a fake Steam.
+mod common;
+
+use std::io;
+use std::path::Path;
+use opal::controller::{install_controller_templates, ControllerLayout};
+use opal::merge::MergePolicy;
+use opal::platform::{FileIo, SteamProcess};
+use opal::compat::available_compat_tools;
+use opal::steam::{default_steam_shortcuts_path, default_steam_user, edit_steam_shortcuts, read_steam_shortcuts, write_steam_shortcuts, DesiredShortcut, ShortcutPlacement};
+use opal::sync::launched_instance;
+use common::*;
+
+const SHORTCUTS : &str = "/steam/userdata/123/config/shortcuts.vdf";
+
+fn desired(folder: &str, name: &str) -> DesiredShortcut {
+    DesiredShortcut {
+        app_name: name.to_string(),
+        exe: String::from("/prism/prismlauncher"),
+        start_dir: String::from("/prism"),
+        icon: String::new(),
+        launch_options: format!("-l \"{}\"", folder),
+        tags: vec![String::from("Installed")],
+        shortcut_path: String::new(),
+        is_hidden: false,
+        allow_overlay: true,
+        allow_desktop_config: true,
+        open_vr: false,
+        last_play_time: 0,
+        explicit: Vec::new(),
+    }
+}
+
+fn files_with_user_shortcut() -> MemoryFiles {
+    let files = MemoryFiles::default();
+    files.insert(SHORTCUTS, user_shortcuts());
+    files
+}
+
+fn names(files: &MemoryFiles) -> Vec<String> {
+    read_steam_shortcuts(files, Path::new(SHORTCUTS)).unwrap().into_iter().map(|s| s.app_name).collect()
+}
+
+#[test]
+fn new_shortcuts_go_after_existing_ones() {
+    let files = files_with_user_shortcut();
+    let path = Path::new(SHORTCUTS);
+    write_steam_shortcuts(&files, path, vec![desired("ATM9", "All the Mods 9"), desired("Vanilla", "Vanilla")],
+        &MergePolicy::default(), ShortcutPlacement::End, &[]).unwrap();
+
+    let shortcuts = read_steam_shortcuts(&files, path).unwrap();
+    assert_eq!(names(&files), ["Some Game", "All the Mods 9", "Vanilla"]);
+    assert_eq!(shortcuts.iter().map(|s| s.order.as_str()).collect::<Vec<_>>(), ["0", "1", "2"]);
+    assert_eq!(launched_instance(&shortcuts[1]).as_deref(), Some("ATM9"));
+    assert_eq!(shortcuts[0].launch_options, "--fullscreen");
+}
+
+#[test]
+fn exporting_again_updates_shortcuts_in_place() {
+    let files = files_with_user_shortcut();
+    let path = Path::new(SHORTCUTS);
+    let policy = MergePolicy::default();
+    write_steam_shortcuts(&files, path, vec![desired("ATM9", "All the Mods 9")], &policy, ShortcutPlacement::End, &[]).unwrap();
+    let app_id = read_steam_shortcuts(&files, path).unwrap()[1].app_id;
+
+    // A renamed shortcut gets a different app_id, but it launches the same instance.
+    write_steam_shortcuts(&files, path, vec![desired("ATM9", "ATM9 (NeoForge)")], &policy, ShortcutPlacement::End, &[]).unwrap();
+
+    let shortcuts = read_steam_shortcuts(&files, path).unwrap();
+    assert_eq!(names(&files), ["Some Game", "ATM9 (NeoForge)"]);
+    assert_eq!(shortcuts[1].app_id, app_id);
+}
+
+#[test]
+fn shortcuts_of_removed_accounts_are_reused_or_dropped() {
+    let files = files_with_user_shortcut();
+    let path = Path::new(SHORTCUTS);
+    let policy = MergePolicy::default();
+    let for_account = |account: &str| DesiredShortcut {
+        launch_options: format!("-l \"ATM9\" -a \"{}\"", account),
+        ..desired("ATM9", &format!("All the Mods 9 ({})", account))
+    };
+    write_steam_shortcuts(&files, path, vec![for_account("Alex"), for_account("Steve")], &policy, ShortcutPlacement::End, &[]).unwrap();
+    let app_id = read_steam_shortcuts(&files, path).unwrap()[1].app_id;
+
+    write_steam_shortcuts(&files, path, vec![desired("ATM9", "All the Mods 9")], &policy, ShortcutPlacement::End, &[]).unwrap();
+
+    let shortcuts = read_steam_shortcuts(&files, path).unwrap();
+    assert_eq!(names(&files), ["Some Game", "All the Mods 9"]);
+    assert_eq!(shortcuts[1].app_id, app_id);
+    assert_eq!(shortcuts[1].launch_options, "-l \"ATM9\"");
+}
+
+#[test]
+fn controller_configs_are_keyed_by_shortcut_name() {
+    let files = MemoryFiles::default();
+    let config_dir = Path::new("/steam/steamapps/common/Steam Controller Configs/123/config");
+    let layouts = [(String::from("All the Mods 9"), ControllerLayout::Controlify)];
+
+    assert_eq!(install_controller_templates(&files, config_dir, &layouts, false).unwrap(), 7);
+
+    assert!(files.get(&config_dir.join("all the mods 9").join("controller_neptune.vdf")).is_some());
+    let set = String::from_utf8(files.get(&config_dir.join("configset_controller_neptune.vdf")).unwrap()).unwrap();
+    let set = keyvalues_parser::Vdf::parse(&set).unwrap();
+    let apps = set.value.get_obj().unwrap();
+    assert_eq!(apps.keys().map(|k| k.as_ref()).collect::<Vec<_>>(), ["all the mods 9"]);
+
+    // Nothing left to do the second time.
+    assert_eq!(install_controller_templates(&files, config_dir, &layouts, false).unwrap(), 0);
+}
+
+#[test]
+fn sorted_and_grouped_placement() {
+    let files = files_with_user_shortcut();
+    let path = Path::new(SHORTCUTS);
+    let policy = MergePolicy::default();
+    write_steam_shortcuts(&files, path, vec![desired("Zeta", "Zeta"), desired("Alpha", "Alpha")],
+        &policy, ShortcutPlacement::EndSortedByName, &[]).unwrap();
+    assert_eq!(names(&files), ["Some Game", "Alpha", "Zeta"]);
+
+    edit_steam_shortcuts(&files, path, |shortcuts| shortcuts.rotate_left(1)).unwrap();
+    assert_eq!(names(&files), ["Alpha", "Zeta", "Some Game"]);
+
+    write_steam_shortcuts(&files, path, vec![desired("Mid", "Mid")], &policy, ShortcutPlacement::WithOpalShortcuts, &[]).unwrap();
+    assert_eq!(names(&files), ["Alpha", "Zeta", "Mid", "Some Game"]);
+}
+
+#[test]
+fn numbers_survive_being_read_back() {
+    let files = files_with_user_shortcut();
+    let path = Path::new(SHORTCUTS);
+    let hidden = DesiredShortcut { is_hidden: true, open_vr: true, ..desired("ATM9", "All the Mods 9") };
+    write_steam_shortcuts(&files, path, vec![hidden], &MergePolicy::default(), ShortcutPlacement::End, &[]).unwrap();
+    // Numbers whose lowest byte is 1 are the ones that are easy to misread.
+    edit_steam_shortcuts(&files, path, |shortcuts| {
+        shortcuts[1].app_id = 0x8123_4501;
+        shortcuts[1].last_play_time = 0x6543_2101;
+    }).unwrap();
+
+    let shortcuts = read_steam_shortcuts(&files, path).unwrap();
+    assert!(!shortcuts[0].is_hidden);
+    assert!(shortcuts[1].is_hidden);
+    assert_eq!(shortcuts[1].open_vr, 1);
+    assert_eq!(shortcuts[1].app_id, 0x8123_4501);
+    assert_eq!(shortcuts[1].last_play_time, 0x6543_2101);
+}
+
+#[test]
+fn writing_needs_an_existing_shortcuts_file() {
+    let files = MemoryFiles::default();
+    let path = Path::new(SHORTCUTS);
+    let result = write_steam_shortcuts(&files, path, vec![desired("ATM9", "All the Mods 9")],
+        &MergePolicy::default(), ShortcutPlacement::End, &[]);
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+    assert!(!files.exists(path));
+}
+
+#[test]
+fn broken_shortcuts_file_is_left_alone() {
+    let files = MemoryFiles::default();
+    files.insert(SHORTCUTS, b"not a vdf".to_vec());
+    let path = Path::new(SHORTCUTS);
+    let result = write_steam_shortcuts(&files, path, vec![desired("ATM9", "All the Mods 9")],
+        &MergePolicy::default(), ShortcutPlacement::End, &[]);
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(files.get(path).unwrap(), b"not a vdf");
+}
+
+#[test]
+fn default_shortcuts_path_is_in_the_located_steam() {
+    let locator = FakeSteamLocator { dir: Some("/home/me/.steam/steam".into()) };
+    let path = default_steam_shortcuts_path(&locator, 42).unwrap();
+    assert_eq!(path, Path::new("/home/me/.steam/steam/userdata/42/config/shortcuts.vdf"));
+
+    let missing = default_steam_shortcuts_path(&FakeSteamLocator::default(), 42);
+    assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn default_user_is_the_last_one_logged_in() {
+    let locator = FakeSteamLocator { dir: Some("/steam".into()) };
+    let files = MemoryFiles::default();
+    files.insert("/steam/userdata/0/config/localconfig.vdf", Vec::new());
+    files.insert("/steam/userdata/456/config/localconfig.vdf", Vec::new());
+    files.insert(SHORTCUTS, user_shortcuts());
+    assert_eq!(default_steam_user(&locator, &files).unwrap(), 123);
+
+    files.insert("/steam/config/loginusers.vdf", r#""users"
+{
+    "76561197960266517" { "AccountName" "other" "MostRecent" "0" }
+    "76561197960266184" { "AccountName" "me" "MostRecent" "1" }
+}"#);
+    assert_eq!(default_steam_user(&locator, &files).unwrap(), 456);
+
+    let nobody = default_steam_user(&locator, &MemoryFiles::default());
+    assert_eq!(nobody.unwrap_err().kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn compat_tools_are_folders_with_a_manifest() {
+    let files = files_with_user_shortcut();
+    files.insert("/steam/compatibilitytools.d/GE-Proton9-1/compatibilitytool.vdf", Vec::new());
+    files.insert("/steam/compatibilitytools.d/readme.txt", Vec::new());
+    assert_eq!(available_compat_tools(&files, Path::new(SHORTCUTS)), ["GE-Proton9-1"]);
+}
+
+#[test]
+fn steam_is_closed_while_shortcuts_are_edited() {
+    let dir = TempDir::new("steam-closed");
+    fake_prism(dir.path());
+    let steam = FakeSteam::running();
+    let mut model = fake_model(dir.path(), steam.clone(), Box::new(MemoryFiles::default()));
+
+    let was_running = model.while_steam_closed(|model| Ok(model.platform.steam.is_running())).unwrap();
+
+    assert!(!was_running);
+    assert!(steam.is_running());
+    assert_eq!(steam.events(), [SteamEvent::Stopped, SteamEvent::Started]);
+}